@@ -11,7 +11,7 @@ use crate::database::MainT;
 use crate::{RankedMap, MResult};
 use crate::settings::RankingRule;
 use crate::{FstSetCow, FstMapCow};
-use super::{CowSet, DocumentsIds};
+use super::{CowSet, DocumentsIds, SchemaCodec};
 
 const ATTRIBUTES_FOR_FACETING_KEY: &str = "attributes-for-faceting";
 const CREATED_AT_KEY: &str = "created-at";
@@ -175,11 +175,11 @@ impl Main {
     }
 
     pub fn put_schema(self, writer: &mut heed::RwTxn<MainT>, schema: &Schema) -> MResult<()> {
-        Ok(self.main.put::<_, Str, SerdeBincode<Schema>>(writer, SCHEMA_KEY, schema)?)
+        Ok(self.main.put::<_, Str, SchemaCodec>(writer, SCHEMA_KEY, schema)?)
     }
 
     pub fn schema(self, reader: &heed::RoTxn<MainT>) -> MResult<Option<Schema>> {
-        Ok(self.main.get::<_, Str, SerdeBincode<Schema>>(reader, SCHEMA_KEY)?)
+        Ok(self.main.get::<_, Str, SchemaCodec>(reader, SCHEMA_KEY)?)
     }
 
     pub fn delete_schema(self, writer: &mut heed::RwTxn<MainT>) -> MResult<bool> {
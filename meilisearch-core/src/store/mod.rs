@@ -8,6 +8,7 @@ mod main;
 mod postings_lists;
 mod prefix_documents_cache;
 mod prefix_postings_lists_cache;
+mod schema_codec;
 mod synonyms;
 mod updates;
 mod updates_results;
@@ -22,6 +23,7 @@ pub use self::main::Main;
 pub use self::postings_lists::PostingsLists;
 pub use self::prefix_documents_cache::PrefixDocumentsCache;
 pub use self::prefix_postings_lists_cache::PrefixPostingsListsCache;
+pub use self::schema_codec::SchemaCodec;
 pub use self::synonyms::Synonyms;
 pub use self::updates::Updates;
 pub use self::updates_results::UpdatesResults;
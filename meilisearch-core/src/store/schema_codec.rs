@@ -0,0 +1,53 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use heed::{BytesEncode, BytesDecode};
+use meilisearch_schema::{LegacySchema, Schema};
+
+// prefixed to the stored schemas, the schemas stored without it have the `LegacySchema` layout.
+// A legacy schema starts with its number of fields as a little endian u64, these bytes read
+// that way give a number far above the maximum number of fields, the two can't be confused.
+const SCHEMA_V1_HEADER: [u8; 8] = *b"\xffschema\x01";
+
+/// Encodes a schema with bincode behind a version header and decodes the schemas stored
+/// before the header was introduced by converting them from their legacy layout.
+pub struct SchemaCodec;
+
+impl<'a> BytesEncode<'a> for SchemaCodec {
+    type EItem = Schema;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Option<Cow<'a, [u8]>> {
+        let mut bytes = SCHEMA_V1_HEADER.to_vec();
+        bincode::serialize_into(&mut bytes, item).ok()?;
+        Some(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> BytesDecode<'a> for SchemaCodec {
+    type DItem = Schema;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        if bytes.starts_with(&SCHEMA_V1_HEADER) {
+            bincode::deserialize(&bytes[SCHEMA_V1_HEADER.len()..]).ok()
+        } else {
+            bincode::deserialize::<LegacySchema>(bytes).ok().and_then(|legacy| Schema::try_from(legacy).ok())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_round_trip() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "overview"]).unwrap();
+        schema.update_ranked(vec!["release_date"]).unwrap();
+
+        let bytes = SchemaCodec::bytes_encode(&schema).unwrap();
+        assert!(bytes.starts_with(&SCHEMA_V1_HEADER));
+        let decoded = SchemaCodec::bytes_decode(&bytes).unwrap();
+        assert!(decoded.semantically_eq(&schema));
+    }
+}
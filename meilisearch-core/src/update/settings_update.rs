@@ -36,7 +36,7 @@ pub fn apply_settings_update(
         Some(schema) => schema,
         None => {
             match settings.primary_key.clone() {
-                UpdateState::Update(id) => Schema::with_primary_key(&id),
+                UpdateState::Update(id) => Schema::try_with_primary_key(&id)?,
                 _ => return Err(Error::MissingPrimaryKey)
             }
         }
@@ -71,14 +71,14 @@ pub fn apply_settings_update(
     match settings.searchable_attributes.clone() {
         UpdateState::Update(v) => {
             if v.iter().any(|e| e == "*") || v.is_empty() {
-                schema.set_all_fields_as_indexed();
+                schema.set_all_fields_as_indexed()?;
             } else {
                 schema.update_indexed(v)?;
             }
             must_reindex = true;
         },
         UpdateState::Clear => {
-            schema.set_all_fields_as_indexed();
+            schema.set_all_fields_as_indexed()?;
             must_reindex = true;
         },
         UpdateState::Nothing => (),
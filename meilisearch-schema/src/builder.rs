@@ -0,0 +1,114 @@
+use crate::{Error, SResult, Schema};
+
+/// Builds a `Schema` in a single expression, the settings are only applied
+/// and validated when calling `build`.
+#[derive(Debug, Default, Clone)]
+pub struct SchemaBuilder {
+    primary_key: Option<String>,
+    require_primary_key: bool,
+    case_insensitive: bool,
+    indexed: Option<Vec<String>>,
+    displayed: Option<Vec<String>>,
+    ranked: Vec<String>,
+}
+
+impl SchemaBuilder {
+    pub fn new() -> SchemaBuilder {
+        SchemaBuilder::default()
+    }
+
+    pub fn primary_key(mut self, name: &str) -> SchemaBuilder {
+        self.primary_key = Some(name.to_string());
+        self
+    }
+
+    /// Makes `build` fail if no primary key has been given.
+    pub fn require_primary_key(mut self) -> SchemaBuilder {
+        self.require_primary_key = true;
+        self
+    }
+
+    /// Makes the built schema resolve field names ignoring their case.
+    pub fn case_insensitive(mut self) -> SchemaBuilder {
+        self.case_insensitive = true;
+        self
+    }
+
+    pub fn indexed<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> SchemaBuilder {
+        self.indexed = Some(names.into_iter().map(|s| s.as_ref().to_string()).collect());
+        self
+    }
+
+    pub fn displayed<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> SchemaBuilder {
+        self.displayed = Some(names.into_iter().map(|s| s.as_ref().to_string()).collect());
+        self
+    }
+
+    pub fn ranked<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> SchemaBuilder {
+        self.ranked = names.into_iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    pub fn build(self) -> SResult<Schema> {
+        let mut schema = if self.case_insensitive { Schema::case_insensitive() } else { Schema::new() };
+
+        match self.primary_key {
+            Some(name) => {
+                schema.set_primary_key(&name)?;
+            }
+            None if self.require_primary_key => return Err(Error::MissingPrimaryKey),
+            None => (),
+        }
+        if let Some(indexed) = self.indexed {
+            schema.update_indexed(indexed)?;
+        }
+        if let Some(displayed) = self.displayed {
+            schema.update_displayed(displayed)?;
+        }
+        schema.update_ranked(self.ranked)?;
+
+        Ok(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedPos;
+
+    #[test]
+    fn schema_builder() {
+        let schema = SchemaBuilder::new()
+            .primary_key("id")
+            .indexed(vec!["title", "description", "id"])
+            .displayed(vec!["title", "id"])
+            .ranked(vec!["release_date"])
+            .build()
+            .unwrap();
+
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert_eq!(schema.id("id"), Some(0.into()));
+        assert_eq!(schema.id("title"), Some(1.into()));
+        assert_eq!(schema.id("description"), Some(2.into()));
+        assert_eq!(schema.id("release_date"), Some(3.into()));
+        assert_eq!(schema.indexed_name(), vec!["title", "description", "id"]);
+        assert_eq!(schema.is_indexed(0.into()), Some(&IndexedPos(2)));
+        assert_eq!(schema.is_indexed(1.into()), Some(&IndexedPos(0)));
+        assert_eq!(schema.is_indexed(2.into()), Some(&IndexedPos(1)));
+        assert_eq!(schema.displayed_name(), vec!["title", "id"].into_iter().collect());
+        assert!(schema.is_ranked(3.into()));
+    }
+
+    #[test]
+    fn schema_builder_errors() {
+        let result = SchemaBuilder::new().indexed(vec!["title"]).require_primary_key().build();
+        assert!(matches!(result, Err(Error::MissingPrimaryKey)));
+
+        let result = SchemaBuilder::new().primary_key("id").displayed(vec!["title", ""]).build();
+        assert!(matches!(result, Err(Error::InvalidFields(names)) if names == vec![String::new()]));
+
+        let schema = SchemaBuilder::new().build().unwrap();
+        assert!(schema.is_indexed_all());
+        assert!(schema.is_displayed_all());
+    }
+}
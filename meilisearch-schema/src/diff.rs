@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::Schema;
+
+/// A setting value before and after a change.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    fn between(old: T, new: T) -> Option<Change<T>> {
+        if old == new {
+            None
+        } else {
+            Some(Change { old, new })
+        }
+    }
+}
+
+/// The differences between two schemas, fields are compared by name so that
+/// schemas built independently can be compared.
+///
+/// The displayed and indexed attributes are `None` when all the fields are displayed or indexed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDiff {
+    pub added_fields: BTreeSet<String>,
+    pub removed_fields: BTreeSet<String>,
+    pub primary_key: Option<Change<Option<String>>>,
+    pub ranked: Option<Change<BTreeSet<String>>>,
+    pub displayed: Option<Change<Option<BTreeSet<String>>>>,
+    pub indexed: Option<Change<Option<Vec<String>>>>,
+    pub faceted: Option<Change<BTreeSet<String>>>,
+    pub distinct: Option<Change<Option<String>>>,
+}
+
+impl SchemaDiff {
+    pub fn new(old: &Schema, new: &Schema) -> SchemaDiff {
+        let old_fields = names(old.names());
+        let new_fields = names(new.names());
+
+        SchemaDiff {
+            added_fields: new_fields.difference(&old_fields).cloned().collect(),
+            removed_fields: old_fields.difference(&new_fields).cloned().collect(),
+            primary_key: Change::between(
+                old.primary_key().map(ToOwned::to_owned),
+                new.primary_key().map(ToOwned::to_owned),
+            ),
+            ranked: Change::between(names(old.ranked_name()), names(new.ranked_name())),
+            displayed: Change::between(displayed(old), displayed(new)),
+            indexed: Change::between(indexed(old), indexed(new)),
+            faceted: Change::between(names(old.faceted_names()), names(new.faceted_names())),
+            distinct: Change::between(
+                old.distinct_name().map(ToOwned::to_owned),
+                new.distinct_name().map(ToOwned::to_owned),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &SchemaDiff::default()
+    }
+}
+
+fn names<'a>(names: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+    names.into_iter().map(ToOwned::to_owned).collect()
+}
+
+fn displayed(schema: &Schema) -> Option<BTreeSet<String>> {
+    if schema.is_displayed_all() {
+        None
+    } else {
+        Some(names(schema.displayed_name()))
+    }
+}
+
+fn indexed(schema: &Schema) -> Option<Vec<String>> {
+    if schema.is_indexed_all() {
+        None
+    } else {
+        Some(schema.indexed_name().into_iter().map(ToOwned::to_owned).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "description", "id"]).unwrap();
+        schema.update_displayed(vec!["title"]).unwrap();
+        schema
+    }
+
+    #[test]
+    fn schema_diff() {
+        let old = schema();
+        assert!(old.diff(&schema()).is_empty());
+
+        let mut new = Schema::new();
+        new.set_primary_key("uid").unwrap();
+        new.update_indexed(vec!["description", "title", "id", "uid"]).unwrap();
+        new.update_displayed(vec!["title", "description"]).unwrap();
+
+        let diff = old.diff(&new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.added_fields, names(vec!["uid"]));
+        assert!(diff.removed_fields.is_empty());
+        assert_eq!(diff.primary_key, Some(Change { old: Some("id".to_string()), new: Some("uid".to_string()) }));
+        assert_eq!(diff.ranked, None);
+        assert_eq!(diff.displayed, Some(Change {
+            old: Some(names(vec!["title"])),
+            new: Some(names(vec!["title", "description"])),
+        }));
+        let indexed = diff.indexed.unwrap();
+        assert_eq!(indexed.old, Some(vec!["title".into(), "description".into(), "id".into()]));
+        assert_eq!(indexed.new, Some(vec!["description".into(), "title".into(), "id".into(), "uid".into()]));
+    }
+
+    #[test]
+    fn schema_diff_position_move() {
+        let old = schema();
+        let mut new = schema();
+        new.move_indexed_relative("id", -2).unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.indexed, Some(Change {
+            old: Some(vec!["title".into(), "description".into(), "id".into()]),
+            new: Some(vec!["id".into(), "title".into(), "description".into()]),
+        }));
+        assert_eq!(diff, SchemaDiff { indexed: diff.indexed.clone(), ..SchemaDiff::default() });
+    }
+
+    #[test]
+    fn schema_diff_faceted_distinct() {
+        let old = schema();
+        let mut new = schema();
+        new.set_faceted("title").unwrap();
+        new.set_distinct("id").unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.faceted, Some(Change { old: names(Vec::new()), new: names(vec!["title"]) }));
+        assert_eq!(diff.distinct, Some(Change { old: None, new: Some("id".to_string()) }));
+        assert_eq!(diff.added_fields, BTreeSet::new());
+    }
+}
@@ -2,13 +2,93 @@ use std::{error, fmt};
 
 use meilisearch_error::{ErrorCode, Code};
 
+use crate::{FieldId, IndexedPos};
+
 pub type SResult<T> = Result<T, Error>;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     FieldNameNotFound(String),
     PrimaryKeyAlreadyPresent,
     MaxFieldsLimitExceeded,
+    PositionOutOfRange(IndexedPos),
+    InvalidFieldName(String, FieldNameError),
+    InconsistentSchema(String),
+    FieldIdNotReserved(FieldId),
+    FieldNameAlreadyExists(String),
+    PrimaryKeyCannotBeRemoved,
+    MissingPrimaryKey,
+    WildcardNotAlone,
+    SchemaLocked(String),
+    FieldIdNotFound(FieldId),
+    IndexedOrderMismatch,
+    InvalidFields(Vec<String>),
+    AmbiguousPrimaryKey(Vec<String>),
+    FieldsCannotBeRemoved(Vec<String>),
+    FieldIdInUse(FieldId),
+}
+
+/// The category of an `Error`, to handle errors without matching every variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The operation conflicts with the current state of the schema.
+    Conflict,
+    /// A field or a position doesn't exist.
+    NotFound,
+    /// The given names or attributes are invalid.
+    Validation,
+    /// The maximum number of fields has been reached.
+    Limit,
+    /// The schema itself is corrupted.
+    Internal,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        use self::Error::*;
+        match self {
+            PrimaryKeyAlreadyPresent
+            | FieldNameAlreadyExists(_)
+            | PrimaryKeyCannotBeRemoved
+            | FieldsCannotBeRemoved(_)
+            | FieldIdInUse(_)
+            | SchemaLocked(_) => ErrorKind::Conflict,
+            FieldNameNotFound(_) | PositionOutOfRange(_) | FieldIdNotFound(_) => ErrorKind::NotFound,
+            InvalidFieldName(..)
+            | FieldIdNotReserved(_)
+            | MissingPrimaryKey
+            | WildcardNotAlone
+            | IndexedOrderMismatch
+            | InvalidFields(_)
+            | AmbiguousPrimaryKey(_) => ErrorKind::Validation,
+            MaxFieldsLimitExceeded => ErrorKind::Limit,
+            InconsistentSchema(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// The reason why a field name was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldNameError {
+    Empty,
+    Blank,
+    TooLong(usize),
+    ControlCharacter,
+    EmptyPathSegment,
+}
+
+impl fmt::Display for FieldNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::FieldNameError::*;
+        match self {
+            Empty => write!(f, "it is empty"),
+            Blank => write!(f, "it only contains whitespaces"),
+            TooLong(max) => write!(f, "it is longer than {} bytes", max),
+            ControlCharacter => write!(f, "it contains a control character"),
+            EmptyPathSegment => write!(f, "it contains an empty path segment"),
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -18,6 +98,21 @@ impl fmt::Display for Error {
             FieldNameNotFound(field) => write!(f, "The field {:?} doesn't exist", field),
             PrimaryKeyAlreadyPresent => write!(f, "A primary key is already present. It's impossible to update it"),
             MaxFieldsLimitExceeded => write!(f, "The maximum of possible reattributed field id has been reached"),
+            PositionOutOfRange(pos) => write!(f, "No field is indexed at position {}", pos),
+            InvalidFieldName(field, reason) => write!(f, "The field name {:?} is invalid: {}", field, reason),
+            InconsistentSchema(reason) => write!(f, "The schema is inconsistent: {}", reason),
+            FieldIdNotReserved(id) => write!(f, "The field id {} is not reserved", id),
+            FieldNameAlreadyExists(field) => write!(f, "The field {:?} already exists", field),
+            PrimaryKeyCannotBeRemoved => write!(f, "The primary key field can't be removed"),
+            MissingPrimaryKey => write!(f, "A primary key is required"),
+            WildcardNotAlone => write!(f, "The wildcard \"*\" can't be used along with other attributes"),
+            SchemaLocked(field) => write!(f, "The schema doesn't accept new fields, {:?} is unknown", field),
+            FieldIdNotFound(id) => write!(f, "The field id {} doesn't exist", id),
+            IndexedOrderMismatch => write!(f, "The new order must contain exactly the indexed attributes"),
+            InvalidFields(fields) => write!(f, "The field names {:?} are invalid", fields),
+            FieldsCannotBeRemoved(fields) => write!(f, "The fields {:?} can't be removed", fields),
+            FieldIdInUse(id) => write!(f, "The field id {} is still in use", id),
+            AmbiguousPrimaryKey(fields) => write!(f, "The primary key can't be guessed among the fields {:?}", fields),
         }
     }
 }
@@ -32,6 +127,79 @@ impl ErrorCode for Error {
             FieldNameNotFound(_) => Code::Internal,
             MaxFieldsLimitExceeded => Code::MaxFieldsLimitExceeded,
             PrimaryKeyAlreadyPresent => Code::PrimaryKeyAlreadyPresent,
+            PrimaryKeyCannotBeRemoved | FieldsCannotBeRemoved(_) => Code::BadRequest,
+            MissingPrimaryKey => Code::MissingPrimaryKey,
+            WildcardNotAlone | SchemaLocked(_) => Code::BadRequest,
+            FieldIdNotFound(_) | IndexedOrderMismatch => Code::BadRequest,
+            InvalidFields(_) | AmbiguousPrimaryKey(_) => Code::BadRequest,
+            PositionOutOfRange(_) | InconsistentSchema(_) | FieldIdNotReserved(_) => Code::Internal,
+            FieldIdInUse(_) => Code::Internal,
+            FieldNameAlreadyExists(_) => Code::BadRequest,
+            InvalidFieldName(..) => Code::BadRequest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Error::PrimaryKeyAlreadyPresent.to_string(),
+            "A primary key is already present. It's impossible to update it",
+        );
+        assert_eq!(
+            Error::InvalidFieldName("ti\ntle".to_string(), FieldNameError::ControlCharacter).to_string(),
+            "The field name \"ti\\ntle\" is invalid: it contains a control character",
+        );
+        assert_eq!(
+            Error::InvalidFieldName(String::new(), FieldNameError::TooLong(512)).to_string(),
+            "The field name \"\" is invalid: it is longer than 512 bytes",
+        );
+    }
+
+    #[test]
+    fn display_ids() {
+        assert_eq!(Error::PositionOutOfRange(IndexedPos(3)).to_string(), "No field is indexed at position 3");
+        assert_eq!(Error::FieldIdNotFound(FieldId(5)).to_string(), "The field id 5 doesn't exist");
+    }
+
+    #[test]
+    fn kind() {
+        use self::ErrorKind::*;
+
+        let errors = vec![
+            (Error::FieldNameNotFound("title".to_string()), NotFound),
+            (Error::PrimaryKeyAlreadyPresent, Conflict),
+            (Error::MaxFieldsLimitExceeded, Limit),
+            (Error::PositionOutOfRange(IndexedPos(3)), NotFound),
+            (Error::InvalidFieldName(String::new(), FieldNameError::Empty), Validation),
+            (Error::InconsistentSchema("unknown field".to_string()), Internal),
+            (Error::FieldIdNotReserved(FieldId(1)), Validation),
+            (Error::FieldNameAlreadyExists("title".to_string()), Conflict),
+            (Error::PrimaryKeyCannotBeRemoved, Conflict),
+            (Error::FieldsCannotBeRemoved(vec!["id".to_string()]), Conflict),
+            (Error::FieldIdInUse(FieldId(1)), Conflict),
+            (Error::MissingPrimaryKey, Validation),
+            (Error::WildcardNotAlone, Validation),
+            (Error::SchemaLocked("title".to_string()), Conflict),
+            (Error::FieldIdNotFound(FieldId(1)), NotFound),
+            (Error::IndexedOrderMismatch, Validation),
+            (Error::InvalidFields(vec![String::new()]), Validation),
+            (Error::AmbiguousPrimaryKey(vec!["id".to_string(), "movie_id".to_string()]), Validation),
+        ];
+        for (error, kind) in errors {
+            assert_eq!(error.kind(), kind, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn boxed_error() {
+        fn fails() -> Result<(), Box<dyn error::Error>> {
+            Err(Error::MissingPrimaryKey)?
         }
+        assert_eq!(fails().unwrap_err().to_string(), "A primary key is required");
     }
 }
@@ -1,18 +1,69 @@
-use std::collections::HashMap;
-use std::collections::hash_map::Iter;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{SResult, FieldId};
+use crate::{SResult, Error, FieldId};
+use crate::legacy::LegacyFieldsMap;
+use crate::ordered::{ordered_map, ordered_set};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FieldsMap {
+    #[serde(serialize_with = "ordered_map")]
     name_map: HashMap<String, FieldId>,
+    #[serde(serialize_with = "ordered_map")]
     id_map: HashMap<FieldId, String>,
-    next_id: FieldId
+    next_id: FieldId,
+    #[serde(default, serialize_with = "ordered_set")]
+    reserved: HashSet<FieldId>,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default, serialize_with = "ordered_map")]
+    aliases: HashMap<String, FieldId>,
+    #[serde(default)]
+    free_ids: BTreeSet<FieldId>,
+}
+
+impl From<LegacyFieldsMap> for FieldsMap {
+    fn from(legacy: LegacyFieldsMap) -> FieldsMap {
+        FieldsMap {
+            name_map: legacy.name_map,
+            id_map: legacy.id_map,
+            next_id: legacy.next_id,
+            ..FieldsMap::default()
+        }
+    }
 }
 
 impl FieldsMap {
+    /// Creates a map able to hold `capacity` fields without reallocating.
+    pub fn with_capacity(capacity: usize) -> FieldsMap {
+        FieldsMap {
+            name_map: HashMap::with_capacity(capacity),
+            id_map: HashMap::with_capacity(capacity),
+            ..FieldsMap::default()
+        }
+    }
+
+    /// Creates a map where names are resolved ignoring their case,
+    /// the first seen casing of a name is the one returned by `name`.
+    pub fn case_insensitive() -> FieldsMap {
+        FieldsMap { case_insensitive: true, ..FieldsMap::default() }
+    }
+
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    // the key under which a name is stored in the name map
+    pub(crate) fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.case_insensitive {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.name_map.len()
     }
@@ -22,33 +73,208 @@ impl FieldsMap {
     }
 
     pub fn insert(&mut self, name: &str) -> SResult<FieldId> {
-        if let Some(id) = self.name_map.get(name) {
-            return Ok(*id)
+        if let Some(id) = self.id(name) {
+            return Ok(id)
+        }
+        let id = self.allocate_id()?;
+        self.name_map.insert(self.key(name).into_owned(), id);
+        self.id_map.insert(id, name.to_string());
+        Ok(id)
+    }
+
+    // reuse the lowest recycled id, if any, to keep ids dense
+    fn allocate_id(&mut self) -> SResult<FieldId> {
+        if let Some(id) = self.free_ids.iter().next().copied() {
+            self.free_ids.remove(&id);
+            return Ok(id);
         }
         let id = self.next_id;
         self.next_id = self.next_id.next()?;
-        self.name_map.insert(name.to_string(), id);
-        self.id_map.insert(id, name.to_string());
         Ok(id)
     }
 
+    /// Allocates a new id that is not bound to any name yet.
+    pub fn reserve(&mut self) -> SResult<FieldId> {
+        let id = self.allocate_id()?;
+        self.reserved.insert(id);
+        Ok(id)
+    }
+
+    /// Binds a name to an id previously allocated by `reserve`.
+    pub fn bind_reserved(&mut self, id: FieldId, name: &str) -> SResult<()> {
+        if !self.reserved.contains(&id) {
+            return Err(Error::FieldIdNotReserved(id));
+        }
+        if self.id(name).is_some() {
+            return Err(Error::FieldNameAlreadyExists(name.to_string()));
+        }
+        self.reserved.remove(&id);
+        self.name_map.insert(self.key(name).into_owned(), id);
+        self.id_map.insert(id, name.to_string());
+        Ok(())
+    }
+
+    /// Makes `alias` resolve to the id of the `target` field. An alias can't shadow a field.
+    pub fn add_alias(&mut self, alias: &str, target: &str) -> SResult<()> {
+        if self.name_map.contains_key(self.key(alias).as_ref()) {
+            return Err(Error::FieldNameAlreadyExists(alias.to_string()));
+        }
+        let id = self.id(target).ok_or_else(|| Error::FieldNameNotFound(target.to_string()))?;
+        self.aliases.insert(self.key(alias).into_owned(), id);
+        Ok(())
+    }
+
+    /// Returns the aliases along with the id they resolve to.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, FieldId)> {
+        self.aliases.iter().map(|(alias, id)| (alias.as_str(), *id))
+    }
+
+    /// Binds the id of the `old` field to the `new` name.
+    pub fn rename(&mut self, old: &str, new: &str) -> SResult<FieldId> {
+        // `old` can be an alias, the current name of the field is the one to replace
+        let ids = self.rename_many(&[(old, new)])?;
+        Ok(ids[0])
+    }
+
+    /// Renames several fields at once, the renames can swap or chain names. Every rename is
+    /// checked before any is applied, so on error the map is left untouched.
+    pub fn rename_many(&mut self, pairs: &[(&str, &str)]) -> SResult<Vec<FieldId>> {
+        let mut ids = Vec::with_capacity(pairs.len());
+        for (old, _) in pairs {
+            match self.id(old) {
+                // a field renamed twice no longer has its old name for the second rename
+                Some(id) if !ids.contains(&id) => ids.push(id),
+                _ => return Err(Error::FieldNameNotFound(old.to_string())),
+            }
+        }
+
+        let mut targets = HashSet::new();
+        for (_, new) in pairs {
+            let taken = matches!(self.id(new), Some(other) if !ids.contains(&other));
+            if taken || !targets.insert(self.key(new)) {
+                return Err(Error::FieldNameAlreadyExists(new.to_string()));
+            }
+        }
+
+        for id in &ids {
+            let key = self.key(&self.id_map[id]).into_owned();
+            self.name_map.remove(&key);
+        }
+        for ((_, new), id) in pairs.iter().zip(&ids) {
+            self.aliases.remove(self.key(new).as_ref());
+            self.name_map.insert(self.key(new).into_owned(), *id);
+            self.id_map.insert(*id, new.to_string());
+        }
+        Ok(ids)
+    }
+
     pub fn remove(&mut self, name: &str) {
-        if let Some(id) = self.name_map.get(name) {
+        if let Some(id) = self.name_map.remove(self.key(name).as_ref()) {
             self.id_map.remove(&id);
+            self.aliases.retain(|_, target| *target != id);
         }
-        self.name_map.remove(name);
     }
 
+    /// Makes ids that are no longer bound to a name available to the next insertions. The ids
+    /// are left untouched if one of them is still bound, reserved or was never allocated.
+    pub fn recycle(&mut self, ids: &[FieldId]) -> SResult<()> {
+        for id in ids {
+            if *id >= self.next_id {
+                return Err(Error::FieldIdNotFound(*id));
+            }
+            if self.id_map.contains_key(id) || self.reserved.contains(id) {
+                return Err(Error::FieldIdInUse(*id));
+            }
+        }
+        self.free_ids.extend(ids);
+        Ok(())
+    }
+
+    /// Resolves a field name, or an alias, to its id.
     pub fn id(&self, name: &str) -> Option<FieldId> {
-        self.name_map.get(name).copied()
+        let key = self.key(name);
+        self.name_map.get(key.as_ref()).or_else(|| self.aliases.get(key.as_ref())).copied()
+    }
+
+    /// Releases the memory left unused after many fields were removed.
+    pub fn shrink_to_fit(&mut self) {
+        self.name_map.shrink_to_fit();
+        self.id_map.shrink_to_fit();
+        self.reserved.shrink_to_fit();
+        self.aliases.shrink_to_fit();
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        let key = self.key(name);
+        self.name_map.contains_key(key.as_ref()) || self.aliases.contains_key(key.as_ref())
     }
 
     pub fn name<I: Into<FieldId>>(&self, id: I) -> Option<&str> {
         self.id_map.get(&id.into()).map(|s| s.as_str())
     }
 
-    pub fn iter(&self) -> Iter<'_, String, FieldId> {
-        self.name_map.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FieldId)> {
+        self.id_map.iter().map(|(id, name)| (name, id))
+    }
+
+    /// Merges the ids that are bound to the same name into the lowest of them,
+    /// returns the mapping from every removed id to the id it was merged into.
+    pub fn deduplicate(&mut self) -> HashMap<FieldId, FieldId> {
+        let mut lowest: HashMap<Cow<str>, FieldId> = HashMap::new();
+        for (&id, name) in &self.id_map {
+            let entry = lowest.entry(self.key(name)).or_insert(id);
+            *entry = (*entry).min(id);
+        }
+
+        let remapping: HashMap<_, _> = self.id_map
+            .iter()
+            .map(|(id, name)| (*id, lowest[&self.key(name)]))
+            .filter(|(id, lowest)| id != lowest)
+            .collect();
+
+        for (id, lowest) in &remapping {
+            if let Some(name) = self.id_map.remove(id) {
+                self.name_map.insert(self.key(&name).into_owned(), *lowest);
+            }
+        }
+        for target in self.aliases.values_mut() {
+            if let Some(lowest) = remapping.get(target) {
+                *target = *lowest;
+            }
+        }
+
+        remapping
+    }
+
+    /// Renumbers the ids, reserved ones included, from `0` in the order of the current ids.
+    /// Returns the mapping from every changed id to its new id.
+    pub fn compact(&mut self) -> HashMap<FieldId, FieldId> {
+        let ids: BTreeSet<_> = self.id_map.keys().chain(&self.reserved).copied().collect();
+        let remapping: HashMap<_, _> = ids
+            .iter()
+            .enumerate()
+            .map(|(new, id)| (*id, FieldId(new as u16)))
+            .filter(|(id, new)| id != new)
+            .collect();
+
+        let remap = |id: FieldId| remapping.get(&id).copied().unwrap_or(id);
+        for id in self.name_map.values_mut().chain(self.aliases.values_mut()) {
+            *id = remap(*id);
+        }
+        self.id_map = self.id_map.drain().map(|(id, name)| (remap(id), name)).collect();
+        self.reserved = self.reserved.iter().copied().map(remap).collect();
+        self.next_id = FieldId(ids.len() as u16);
+        self.free_ids.clear();
+
+        remapping
+    }
+
+    #[cfg(test)]
+    pub(crate) fn bind_unchecked(&mut self, id: FieldId, name: &str) {
+        self.name_map.insert(self.key(name).into_owned(), id);
+        self.id_map.insert(id, name.to_string());
+        self.free_ids.remove(&id);
+        self.next_id = self.next_id.max(id.next().unwrap());
     }
 }
 
@@ -79,4 +305,124 @@ mod tests {
         assert_eq!(fields_map.insert("title").unwrap(), 3.into());
         assert_eq!(fields_map.len(), 3);
     }
+
+    #[test]
+    fn contains() {
+        let mut fields_map = FieldsMap::case_insensitive();
+        fields_map.insert("Title").unwrap();
+        assert!(fields_map.contains("Title"));
+        assert!(fields_map.contains("title"));
+        assert!(!fields_map.contains("overview"));
+        fields_map.remove("title");
+        assert!(!fields_map.contains("Title"));
+    }
+
+    #[test]
+    fn recycle_ids() {
+        let mut fields_map = FieldsMap::default();
+        for name in &["a", "b", "c", "d"] {
+            fields_map.insert(name).unwrap();
+        }
+        fields_map.remove("c");
+        fields_map.remove("b");
+        assert_eq!(fields_map.insert("e").unwrap(), 4.into());
+
+        assert!(matches!(fields_map.recycle(&[FieldId(1), FieldId(0)]), Err(Error::FieldIdInUse(_))));
+        assert!(matches!(fields_map.recycle(&[FieldId(5)]), Err(Error::FieldIdNotFound(_))));
+        fields_map.recycle(&[FieldId(2), FieldId(1)]).unwrap();
+        assert_eq!(fields_map.reserve().unwrap(), 1.into());
+        assert_eq!(fields_map.insert("f").unwrap(), 2.into());
+        assert_eq!(fields_map.insert("g").unwrap(), 5.into());
+        assert_eq!(fields_map.name(2), Some("f"));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut fields_map = FieldsMap::case_insensitive();
+        assert_eq!(fields_map.insert("Title").unwrap(), 0.into());
+        assert_eq!(fields_map.insert("title").unwrap(), 0.into());
+        assert_eq!(fields_map.id("TITLE"), Some(0.into()));
+        assert_eq!(fields_map.name(0), Some("Title"));
+        assert_eq!(fields_map.len(), 1);
+
+        fields_map.rename("title", "Name").unwrap();
+        assert_eq!(fields_map.id("name"), Some(0.into()));
+        assert_eq!(fields_map.name(0), Some("Name"));
+        fields_map.remove("NAME");
+        assert!(fields_map.is_empty());
+
+        let mut fields_map = FieldsMap::default();
+        assert_eq!(fields_map.insert("Title").unwrap(), 0.into());
+        assert_eq!(fields_map.insert("title").unwrap(), 1.into());
+        assert_eq!(fields_map.id("TITLE"), None);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut fields_map = FieldsMap::default();
+        fields_map.insert("id").unwrap();
+        fields_map.insert("name").unwrap();
+        fields_map.add_alias("title", "name").unwrap();
+        assert_eq!(fields_map.id("title"), Some(1.into()));
+        assert_eq!(fields_map.insert("title").unwrap(), 1.into());
+        assert_eq!(fields_map.name(1), Some("name"));
+        assert_eq!(fields_map.len(), 2);
+        assert_eq!(fields_map.aliases().collect::<Vec<_>>(), vec![("title", 1.into())]);
+
+        assert!(matches!(fields_map.add_alias("id", "name"), Err(Error::FieldNameAlreadyExists(_))));
+        assert!(matches!(fields_map.add_alias("label", "unknown"), Err(Error::FieldNameNotFound(_))));
+
+        fields_map.remove("name");
+        assert_eq!(fields_map.id("title"), None);
+        assert_eq!(fields_map.aliases().count(), 0);
+    }
+
+    #[test]
+    fn rename_through_alias() {
+        let mut fields_map = FieldsMap::default();
+        fields_map.insert("id").unwrap();
+        fields_map.insert("name").unwrap();
+        fields_map.add_alias("title", "name").unwrap();
+
+        assert_eq!(fields_map.rename("title", "label").unwrap(), 1.into());
+        assert_eq!(fields_map.id("label"), Some(1.into()));
+        assert_eq!(fields_map.id("name"), None);
+        assert_eq!(fields_map.name(1), Some("label"));
+        assert_eq!(fields_map.len(), 2);
+    }
+
+    #[test]
+    fn reserve() {
+        let mut fields_map = FieldsMap::default();
+        fields_map.insert("id").unwrap();
+        let reserved = fields_map.reserve().unwrap();
+        assert_eq!(reserved, 1.into());
+        assert_eq!(fields_map.insert("title").unwrap(), 2.into());
+        assert_eq!(fields_map.name(reserved), None);
+
+        assert!(matches!(fields_map.bind_reserved(reserved, "title"), Err(Error::FieldNameAlreadyExists(_))));
+        assert!(matches!(fields_map.bind_reserved(2.into(), "date"), Err(Error::FieldIdNotReserved(_))));
+        fields_map.bind_reserved(reserved, "date").unwrap();
+        assert_eq!(fields_map.id("date"), Some(reserved));
+        assert!(matches!(fields_map.bind_reserved(reserved, "other"), Err(Error::FieldIdNotReserved(_))));
+    }
+
+    #[test]
+    fn deduplicate() {
+        let mut fields_map = FieldsMap::default();
+        fields_map.insert("id").unwrap();
+        fields_map.insert("title").unwrap();
+        assert!(fields_map.deduplicate().is_empty());
+
+        fields_map.bind_unchecked(5.into(), "title");
+        fields_map.bind_unchecked(7.into(), "title");
+        let remapping = fields_map.deduplicate();
+        assert_eq!(remapping.len(), 2);
+        assert_eq!(remapping[&5.into()], 1.into());
+        assert_eq!(remapping[&7.into()], 1.into());
+        assert_eq!(fields_map.id("title"), Some(1.into()));
+        assert_eq!(fields_map.name(5), None);
+        assert_eq!(fields_map.name(7), None);
+        assert_eq!(fields_map.len(), 2);
+    }
 }
@@ -0,0 +1,82 @@
+//! The layout of the schemas stored before the fields map and the schema gained their optional
+//! settings. Bincode, the format of the stored schemas, is not self-describing: the fields
+//! marked `#[serde(default)]` can't be missing at the end of a stored schema, so these old
+//! schemas must be decoded with this layout and converted.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::schema::OptionAll;
+use crate::{FieldId, IndexedPos};
+
+/// A schema as it was stored before the schema settings were extended, convert it into a
+/// `Schema` with `Schema::try_from`.
+#[derive(Debug, Deserialize)]
+pub struct LegacySchema {
+    pub(crate) fields_map: LegacyFieldsMap,
+    pub(crate) primary_key: Option<FieldId>,
+    pub(crate) ranked: HashSet<FieldId>,
+    pub(crate) displayed: OptionAll<HashSet<FieldId>>,
+    pub(crate) indexed: OptionAll<Vec<FieldId>>,
+    pub(crate) indexed_map: HashMap<FieldId, IndexedPos>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LegacyFieldsMap {
+    pub(crate) name_map: HashMap<String, FieldId>,
+    pub(crate) id_map: HashMap<FieldId, String>,
+    pub(crate) next_id: FieldId,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use crate::Schema;
+
+    // a schema encoded by the baseline crate: primary key `id`, indexed `title` and `overview`,
+    // displayed `id`, `title` and `poster`, ranked `release_date`
+    const BASELINE_SCHEMA: [u8; 247] = [
+        5, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
+        105, 100, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 112, 111, 115, 116,
+        101, 114, 3, 0, 8, 0, 0, 0, 0, 0, 0, 0, 111, 118, 101, 114,
+        118, 105, 101, 119, 2, 0, 5, 0, 0, 0, 0, 0, 0, 0, 116, 105,
+        116, 108, 101, 1, 0, 12, 0, 0, 0, 0, 0, 0, 0, 114, 101, 108,
+        101, 97, 115, 101, 95, 100, 97, 116, 101, 4, 0, 5, 0, 0, 0, 0,
+        0, 0, 0, 2, 0, 8, 0, 0, 0, 0, 0, 0, 0, 111, 118, 101,
+        114, 118, 105, 101, 119, 1, 0, 5, 0, 0, 0, 0, 0, 0, 0, 116,
+        105, 116, 108, 101, 3, 0, 6, 0, 0, 0, 0, 0, 0, 0, 112, 111,
+        115, 116, 101, 114, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 105, 100,
+        4, 0, 12, 0, 0, 0, 0, 0, 0, 0, 114, 101, 108, 101, 97, 115,
+        101, 95, 100, 97, 116, 101, 5, 0, 1, 0, 0, 1, 0, 0, 0, 0,
+        0, 0, 0, 4, 0, 1, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0,
+        0, 3, 0, 0, 0, 1, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0,
+        0, 0, 0, 1, 0, 2, 0, 2, 0, 0, 0, 0, 0, 0, 0, 2,
+        0, 1, 0, 1, 0, 0, 0,
+    ];
+
+    #[test]
+    fn decode_baseline_schema() {
+        assert!(bincode::deserialize::<Schema>(&BASELINE_SCHEMA).is_err());
+
+        let legacy: LegacySchema = bincode::deserialize(&BASELINE_SCHEMA).unwrap();
+        let schema = Schema::try_from(legacy).unwrap();
+        schema.assert_consistent().unwrap();
+
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert_eq!(schema.id("title"), Some(FieldId(1)));
+        assert_eq!(schema.id("release_date"), Some(FieldId(4)));
+        assert_eq!(schema.indexed_name(), vec!["title", "overview"]);
+        assert_eq!(schema.displayed_name(), vec!["id", "title", "poster"].into_iter().collect());
+        assert_eq!(schema.ranked_name(), vec!["release_date"].into_iter().collect());
+        assert!(schema.positions_are_dense());
+
+        let mut schema = schema;
+        assert_eq!(schema.insert("genre").unwrap(), FieldId(5));
+
+        let bytes = bincode::serialize(&schema).unwrap();
+        let decoded: Schema = bincode::deserialize(&bytes).unwrap();
+        assert!(decoded.semantically_eq(&schema));
+    }
+}
@@ -1,14 +1,29 @@
+mod builder;
+mod diff;
 mod error;
 mod fields_map;
+mod legacy;
+mod named;
+mod ordered;
 mod schema;
+mod settings;
 
-pub use error::{Error, SResult};
+pub use builder::SchemaBuilder;
+pub use diff::{Change, SchemaDiff};
+pub use error::{Error, ErrorKind, FieldNameError, SResult};
 pub use fields_map::FieldsMap;
-pub use schema::Schema;
+pub use legacy::LegacySchema;
+pub use named::NamedSchema;
+pub use schema::{Schema, DocumentValidation, FieldInfo, FieldMetadata, FieldUsageReport, MAX_FIELD_NAME_LEN};
+pub use settings::Settings;
+use std::convert::TryFrom;
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 use zerocopy::{AsBytes, FromBytes};
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[serde(transparent)]
 pub struct IndexedPos(pub u16);
 
 impl IndexedPos {
@@ -23,6 +38,18 @@ impl IndexedPos {
     pub const fn max() -> IndexedPos {
         IndexedPos(u16::max_value())
     }
+
+    pub fn next(self) -> SResult<IndexedPos> {
+        self.checked_add(1).ok_or(Error::MaxFieldsLimitExceeded)
+    }
+
+    pub fn prev(self) -> Option<IndexedPos> {
+        self.0.checked_sub(1).map(IndexedPos)
+    }
+
+    pub fn checked_add(self, rhs: u16) -> Option<IndexedPos> {
+        self.0.checked_add(rhs).map(IndexedPos)
+    }
 }
 
 impl From<u16> for IndexedPos {
@@ -37,8 +64,29 @@ impl Into<u16> for IndexedPos {
     }
 }
 
+impl fmt::Display for IndexedPos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<usize> for IndexedPos {
+    type Error = Error;
+
+    fn try_from(value: usize) -> SResult<IndexedPos> {
+        u16::try_from(value).map(IndexedPos).map_err(|_| Error::MaxFieldsLimitExceeded)
+    }
+}
+
+impl From<IndexedPos> for usize {
+    fn from(pos: IndexedPos) -> usize {
+        pos.0 as usize
+    }
+}
+
 #[derive(Debug, Copy, Clone, Default, PartialOrd, Ord, PartialEq, Eq, Hash)]
 #[derive(Serialize, Deserialize)]
+#[serde(transparent)]
 #[derive(AsBytes, FromBytes)]
 #[repr(C)]
 pub struct FieldId(pub u16);
@@ -61,6 +109,12 @@ impl FieldId {
     }
 }
 
+impl fmt::Display for FieldId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl From<u16> for FieldId {
     fn from(value: u16) -> FieldId {
         FieldId(value)
@@ -72,3 +126,45 @@ impl From<FieldId> for u16 {
         other.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexed_pos_arithmetic() {
+        assert_eq!(IndexedPos(0).next().unwrap(), IndexedPos(1));
+        assert_eq!(IndexedPos(1).prev(), Some(IndexedPos(0)));
+        assert_eq!(IndexedPos(0).prev(), None);
+        assert_eq!(IndexedPos(2).checked_add(3), Some(IndexedPos(5)));
+        assert_eq!(usize::from(IndexedPos(42)), 42);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(FieldId(5).to_string(), "5");
+        assert_eq!(IndexedPos(3).to_string(), "3");
+    }
+
+    #[test]
+    fn indexed_pos_overflow() {
+        let max = u16::MAX as usize;
+        assert_eq!(IndexedPos::try_from(max).unwrap(), IndexedPos::max());
+        assert!(matches!(IndexedPos::try_from(max + 1), Err(Error::MaxFieldsLimitExceeded)));
+        assert!(matches!(IndexedPos::max().next(), Err(Error::MaxFieldsLimitExceeded)));
+        assert_eq!(IndexedPos::max().checked_add(1), None);
+        assert_eq!(IndexedPos(u16::MAX - 1).checked_add(1), Some(IndexedPos::max()));
+    }
+
+    #[test]
+    fn serde_plain_integers() {
+        assert_eq!(serde_json::to_string(&FieldId(3)).unwrap(), "3");
+        assert_eq!(serde_json::to_string(&IndexedPos(7)).unwrap(), "7");
+        assert_eq!(serde_json::from_str::<FieldId>("3").unwrap(), FieldId(3));
+        assert_eq!(serde_json::from_str::<IndexedPos>("7").unwrap(), IndexedPos(7));
+
+        // newtype structs were already written as their inner integer, stored ids still load
+        let stored: Vec<(FieldId, IndexedPos)> = serde_json::from_str("[[0,1],[2,0]]").unwrap();
+        assert_eq!(stored, vec![(FieldId(0), IndexedPos(1)), (FieldId(2), IndexedPos(0))]);
+    }
+}
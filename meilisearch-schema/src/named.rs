@@ -0,0 +1,32 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// A portable representation of a schema where every field is referred to by its name.
+///
+/// Unlike the `Schema` serialization which dumps the raw field ids, it can be edited by hand
+/// and survives a renumbering of the ids: `Schema::from_named` gives the fields their id in
+/// the order they are listed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedSchema {
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    pub primary_key: Option<String>,
+    #[serde(default)]
+    pub distinct: Option<String>,
+    /// The ranked attributes in the order they were set.
+    #[serde(default)]
+    pub ranked: Vec<String>,
+    #[serde(default)]
+    pub faceted: BTreeSet<String>,
+    /// `None` when all the fields are displayed.
+    pub displayed: Option<BTreeSet<String>>,
+    /// `None` when all the fields are indexed.
+    pub indexed: Option<Vec<String>>,
+    /// The fields in position order when all the fields are indexed, the fields that are
+    /// missing from it are positioned last.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positions: Vec<String>,
+}
@@ -0,0 +1,26 @@
+//! Serialization helpers writing hash maps and sets in key order, so that the same schema
+//! is always serialized to the same bytes whatever the order its fields were inserted in.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use serde::{Serialize, Serializer};
+
+pub(crate) fn ordered_map<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Hash + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    let ordered: BTreeMap<_, _> = map.iter().collect();
+    ordered.serialize(serializer)
+}
+
+pub(crate) fn ordered_set<T, S>(set: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Ord + Hash + Serialize,
+    S: Serializer,
+{
+    let ordered: BTreeSet<_> = set.iter().collect();
+    ordered.serialize(serializer)
+}
@@ -1,10 +1,16 @@
-use crate::{FieldsMap, FieldId, SResult, Error, IndexedPos};
-use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, HashSet};
+use crate::{FieldsMap, FieldId, SResult, Error, FieldNameError, IndexedPos, NamedSchema, SchemaDiff, Settings};
+use crate::legacy::LegacySchema;
+use crate::ordered::{ordered_map, ordered_set};
+use indexmap::IndexMap;
+use serde::{Serialize, Serializer, Deserialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-enum OptionAll<T> {
+pub(crate) enum OptionAll<T> {
     All,
     Some(T),
     None,
@@ -35,16 +41,227 @@ impl<T> Default for OptionAll<T> {
     }
 }
 
+// returns true if the attributes only contain the "*" wildcard,
+// the wildcard can't be mixed with other attributes
+fn is_wildcard<S: AsRef<str>>(attributes: &[S]) -> SResult<bool> {
+    match attributes {
+        [attribute] if attribute.as_ref() == "*" => Ok(true),
+        _ if attributes.iter().any(|a| a.as_ref() == "*") => Err(Error::WildcardNotAlone),
+        _ => Ok(false),
+    }
+}
+
+/// The default maximum length of a field name, in bytes.
+pub const MAX_FIELD_NAME_LEN: usize = 512;
+
+/// How a single field takes part in the schema settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMetadata {
+    pub id: FieldId,
+    pub primary_key: bool,
+    pub indexed: bool,
+    pub position: Option<IndexedPos>,
+    pub displayed: bool,
+    pub ranked: bool,
+    pub faceted: bool,
+    pub distinct: bool,
+}
+
+impl FieldMetadata {
+    /// Returns `true` if the field takes part in none of the indexed, displayed, ranked and
+    /// faceted attributes and is not the distinct attribute.
+    pub fn is_empty(&self) -> bool {
+        !(self.indexed || self.displayed || self.ranked || self.faceted || self.distinct)
+    }
+}
+
+/// A field of the schema along with its name, as returned by `Schema::iter_fields`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct FieldInfo<'a> {
+    pub name: &'a str,
+    #[serde(flatten)]
+    pub metadata: FieldMetadata,
+}
+
+/// Per field usage of a schema, keyed by field name and sorted by indexed position
+/// then by name, fields without a position come last.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct FieldUsageReport {
+    fields: IndexMap<String, FieldMetadata>,
+}
+
+impl FieldUsageReport {
+    pub fn get(&self, name: &str) -> Option<&FieldMetadata> {
+        self.fields.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FieldMetadata)> {
+        self.fields.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// The result of checking the keys of a document against a schema.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocumentValidation {
+    /// The keys of the document that are not fields of the schema, in document order.
+    pub unknown_fields: Vec<String>,
+    pub missing_primary_key: bool,
+}
+
+impl DocumentValidation {
+    pub fn is_valid(&self) -> bool {
+        self.unknown_fields.is_empty() && !self.missing_primary_key
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(into = "StoredSchema", try_from = "StoredSchema")]
 pub struct Schema {
     fields_map: FieldsMap,
 
     primary_key: Option<FieldId>,
-    ranked: HashSet<FieldId>,
+    ranked: BTreeSet<FieldId>,
+    // the ranked attributes in the order they were set, `ranked` answers the membership
+    ranked_order: Vec<FieldId>,
     displayed: OptionAll<HashSet<FieldId>>,
 
     indexed: OptionAll<Vec<FieldId>>,
+    // the position of every positioned field, not stored but rebuilt from `StoredSchema`
     indexed_map: HashMap<FieldId, IndexedPos>,
+
+    field_frequencies: HashMap<FieldId, u32>,
+    locked: bool,
+    max_field_name_len: Option<usize>,
+    distinct: Option<FieldId>,
+    faceted: HashSet<FieldId>,
+}
+
+// the serialized layout of a schema, the positions are stored once in position order: by the
+// explicit indexed list or, when all the fields are indexed, by `positions`
+#[derive(Serialize, Deserialize)]
+struct StoredSchema {
+    fields_map: FieldsMap,
+
+    primary_key: Option<FieldId>,
+    ranked: BTreeSet<FieldId>,
+    #[serde(default)]
+    ranked_order: Vec<FieldId>,
+    #[serde(serialize_with = "ordered_displayed")]
+    displayed: OptionAll<HashSet<FieldId>>,
+
+    indexed: OptionAll<Vec<FieldId>>,
+    #[serde(default)]
+    positions: Vec<FieldId>,
+
+    #[serde(default, serialize_with = "ordered_map")]
+    field_frequencies: HashMap<FieldId, u32>,
+
+    #[serde(default)]
+    locked: bool,
+
+    #[serde(default)]
+    max_field_name_len: Option<usize>,
+
+    #[serde(default)]
+    distinct: Option<FieldId>,
+
+    #[serde(default, serialize_with = "ordered_set")]
+    faceted: HashSet<FieldId>,
+}
+
+impl From<Schema> for StoredSchema {
+    fn from(schema: Schema) -> StoredSchema {
+        let positions = if schema.is_indexed_all() { schema.positioned_fields() } else { Vec::new() };
+        StoredSchema {
+            fields_map: schema.fields_map,
+            primary_key: schema.primary_key,
+            ranked: schema.ranked,
+            ranked_order: schema.ranked_order,
+            displayed: schema.displayed,
+            indexed: schema.indexed,
+            positions,
+            field_frequencies: schema.field_frequencies,
+            locked: schema.locked,
+            max_field_name_len: schema.max_field_name_len,
+            distinct: schema.distinct,
+            faceted: schema.faceted,
+        }
+    }
+}
+
+impl TryFrom<StoredSchema> for Schema {
+    type Error = Error;
+
+    fn try_from(stored: StoredSchema) -> SResult<Schema> {
+        let mut schema = Schema {
+            fields_map: stored.fields_map,
+            primary_key: stored.primary_key,
+            ranked: stored.ranked,
+            ranked_order: stored.ranked_order,
+            displayed: stored.displayed,
+            indexed: stored.indexed,
+            indexed_map: HashMap::new(),
+            field_frequencies: stored.field_frequencies,
+            locked: stored.locked,
+            max_field_name_len: stored.max_field_name_len,
+            distinct: stored.distinct,
+            faceted: stored.faceted,
+        };
+
+        let ordered = match schema.indexed {
+            OptionAll::Some(ref v) => v.clone(),
+            _ => stored.positions,
+        };
+        schema.reset_positions(&ordered)?;
+        Ok(schema)
+    }
+}
+
+impl TryFrom<LegacySchema> for Schema {
+    type Error = Error;
+
+    fn try_from(legacy: LegacySchema) -> SResult<Schema> {
+        let ranked: BTreeSet<_> = legacy.ranked.into_iter().collect();
+        let mut schema = Schema {
+            fields_map: FieldsMap::from(legacy.fields_map),
+            primary_key: legacy.primary_key,
+            ranked_order: ranked.iter().copied().collect(),
+            ranked,
+            displayed: legacy.displayed,
+            indexed: legacy.indexed,
+            indexed_map: legacy.indexed_map,
+            ..Schema::default()
+        };
+
+        // the positions were not always kept contiguous nor in the order of the indexed list
+        match schema.indexed.take() {
+            OptionAll::Some(indexed) => {
+                schema.indexed = OptionAll::Some(Vec::new());
+                schema.set_indexed_order(indexed)?;
+            }
+            indexed => {
+                schema.indexed = indexed;
+                let positioned = schema.positioned_fields();
+                schema.reset_positions(&positioned)?;
+            }
+        }
+        Ok(schema)
+    }
+}
+
+// serialize the displayed fields in id order, see the `ordered` module
+fn ordered_displayed<S: Serializer>(displayed: &OptionAll<HashSet<FieldId>>, serializer: S) -> Result<S::Ok, S::Error> {
+    displayed.clone().map(|v| v.into_iter().collect::<BTreeSet<_>>()).serialize(serializer)
 }
 
 impl Schema {
@@ -52,6 +269,29 @@ impl Schema {
         Schema::default()
     }
 
+    /// Creates a schema able to hold `capacity` fields without reallocating, e.g. when the
+    /// number of fields of the documents to index is known.
+    pub fn with_capacity(capacity: usize) -> Schema {
+        Schema {
+            fields_map: FieldsMap::with_capacity(capacity),
+            indexed_map: HashMap::with_capacity(capacity),
+            ..Schema::default()
+        }
+    }
+
+    /// Creates a schema where field names are resolved ignoring their case, e.g. `Title` and
+    /// `title` are the same field. The casing used when the field was first inserted is kept
+    /// for display.
+    pub fn case_insensitive() -> Schema {
+        Schema { fields_map: FieldsMap::case_insensitive(), ..Schema::default() }
+    }
+
+    pub fn is_case_insensitive(&self) -> bool {
+        self.fields_map.is_case_insensitive()
+    }
+
+    /// Creates a schema with `name` as primary key, the name is not validated, see
+    /// `try_with_primary_key` for names coming from a user request.
     pub fn with_primary_key(name: &str) -> Schema {
         let mut fields_map = FieldsMap::default();
         let field_id = fields_map.insert(name).unwrap();
@@ -65,22 +305,248 @@ impl Schema {
         Schema {
             fields_map,
             primary_key: Some(field_id),
-            ranked: HashSet::new(),
+            ranked: BTreeSet::new(),
             displayed: OptionAll::All,
             indexed: OptionAll::All,
             indexed_map,
+            ..Schema::default()
+        }
+    }
+
+    /// Same as `with_primary_key` but returns an error if the name is invalid.
+    pub fn try_with_primary_key(name: &str) -> SResult<Schema> {
+        Schema::validate_field_name(name)?;
+        Ok(Schema::with_primary_key(name))
+    }
+
+    /// Creates a schema from the keys of several documents, every key is indexed and displayed
+    /// and the number of documents containing each of them is recorded.
+    pub fn from_multiple_documents<D, K, S>(documents: D) -> SResult<Schema>
+    where
+        D: IntoIterator<Item = K>,
+        K: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut schema = Schema::new();
+        for keys in documents {
+            let mut ids = HashSet::new();
+            for key in keys {
+                ids.insert(schema.insert_and_index(key.as_ref())?);
+            }
+            for id in ids {
+                *schema.field_frequencies.entry(id).or_insert(0) += 1;
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Creates a schema from the keys of a document, every key is indexed and displayed in the
+    /// order it appears. Nested objects are flattened using the dot notation, e.g. `author.name`.
+    /// The primary key is set if it is given and the document contains it.
+    pub fn from_document(document: &Map<String, Value>, primary_key: Option<&str>) -> SResult<Schema> {
+        fn flatten(prefix: Option<&str>, object: &Map<String, Value>, keys: &mut Vec<String>) {
+            for (key, value) in object {
+                let key = match prefix {
+                    Some(prefix) => format!("{}.{}", prefix, key),
+                    None => key.clone(),
+                };
+                match value {
+                    Value::Object(object) if !object.is_empty() => flatten(Some(&key), object, keys),
+                    _ => keys.push(key),
+                }
+            }
+        }
+
+        let mut keys = Vec::new();
+        flatten(None, document, &mut keys);
+
+        let mut schema = Schema::new();
+        for key in &keys {
+            schema.insert_and_index(key)?;
+        }
+        if let Some(primary_key) = primary_key.filter(|name| schema.contains(name)) {
+            schema.set_primary_key(primary_key)?;
+        }
+        Ok(schema)
+    }
+
+    /// Checks the keys of a document against the schema, reporting the keys that are not
+    /// fields of the schema and whether the primary key is missing from the document.
+    ///
+    /// Returns `MissingPrimaryKey` if the schema itself has no primary key.
+    pub fn validate_against_document(&self, keys: &[&str]) -> SResult<DocumentValidation> {
+        let primary_key = self.primary_key.ok_or(Error::MissingPrimaryKey)?;
+
+        let mut validation = DocumentValidation { missing_primary_key: true, ..Default::default() };
+        for key in keys {
+            match self.fields_map.id(key) {
+                Some(id) if id == primary_key => validation.missing_primary_key = false,
+                Some(_) => (),
+                None => validation.unknown_fields.push(key.to_string()),
+            }
+        }
+        Ok(validation)
+    }
+
+    /// Returns the number of documents containing this field, as seen by
+    /// `from_multiple_documents`.
+    pub fn field_frequency(&self, name: &str) -> Option<u32> {
+        let id = self.fields_map.id(name)?;
+        self.field_frequencies.get(&id).copied()
+    }
+
+    /// Creates a schema with an `id` primary key followed by `field_count` indexed fields
+    /// named `field_0`, `field_1`... to be used for benchmarks and stress tests.
+    #[cfg(any(test, feature = "bench"))]
+    pub fn with_defaults_for(field_count: usize) -> Schema {
+        let mut schema = Schema::with_primary_key("id");
+        for i in 0..field_count {
+            schema.insert_and_index(&format!("field_{}", i)).unwrap();
+        }
+        schema
+    }
+
+    /// Checks that a field name can be inserted in a schema:
+    ///  - it must not be empty nor only made of whitespaces,
+    ///  - it must not be longer than `MAX_FIELD_NAME_LEN` bytes,
+    ///  - it must not contain any control character,
+    ///  - every dot separated segment of it must not be empty (e.g. `author.name`).
+    pub fn validate_field_name(name: &str) -> SResult<()> {
+        Schema::validate_field_name_with_max_len(name, MAX_FIELD_NAME_LEN)
+    }
+
+    /// Same as `validate_field_name` but with a custom maximum length, in bytes.
+    pub fn validate_field_name_with_max_len(name: &str, max_len: usize) -> SResult<()> {
+        let reason = if name.is_empty() {
+            FieldNameError::Empty
+        } else if name.trim().is_empty() {
+            FieldNameError::Blank
+        } else if name.len() > max_len {
+            FieldNameError::TooLong(max_len)
+        } else if name.chars().any(char::is_control) {
+            FieldNameError::ControlCharacter
+        } else if name.split('.').any(str::is_empty) {
+            FieldNameError::EmptyPathSegment
+        } else {
+            return Ok(());
+        };
+        Err(Error::InvalidFieldName(name.to_string(), reason))
+    }
+
+    /// The maximum length, in bytes, of the names of the fields inserted in this schema.
+    pub fn max_field_name_len(&self) -> usize {
+        self.max_field_name_len.unwrap_or(MAX_FIELD_NAME_LEN)
+    }
+
+    /// Changes the maximum length of the names of new fields, known fields are left untouched.
+    pub fn set_max_field_name_len(&mut self, max_len: usize) {
+        self.max_field_name_len = Some(max_len);
+    }
+
+    // insert the field in the fields map, validating the name of unknown fields
+    fn insert_field(&mut self, name: &str) -> SResult<FieldId> {
+        if let Some(id) = self.fields_map.id(name) {
+            return Ok(id);
+        }
+        if self.locked {
+            return Err(Error::SchemaLocked(name.to_string()));
+        }
+        Schema::validate_field_name_with_max_len(name, self.max_field_name_len())?;
+        self.fields_map.insert(name)
+    }
+
+    // check the names of all the unknown fields at once, so that
+    // every invalid name is reported instead of only the first one
+    fn check_new_names<S: AsRef<str>>(&self, names: &[S]) -> SResult<()> {
+        let max_len = self.max_field_name_len();
+        let mut invalid: Vec<String> = Vec::new();
+        for name in names.iter().map(AsRef::as_ref) {
+            if !self.contains(name)
+                && Schema::validate_field_name_with_max_len(name, max_len).is_err()
+                && !invalid.iter().any(|n| n == name)
+            {
+                invalid.push(name.to_string());
+            }
         }
+
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidFields(invalid))
+        }
+    }
+
+    /// Returns `false` if the schema is locked and refuses unknown fields.
+    pub fn accept_new_fields(&self) -> bool {
+        !self.locked
+    }
+
+    /// Locks or unlocks the schema. When locked, inserting an unknown field returns
+    /// `Error::SchemaLocked` while known fields keep resolving normally.
+    pub fn set_accept_new_fields(&mut self, accept: bool) {
+        self.locked = !accept;
     }
 
     pub fn primary_key(&self) -> Option<&str> {
         self.primary_key.map(|id| self.fields_map.name(id).unwrap())
     }
 
+    pub fn primary_key_id(&self) -> Option<FieldId> {
+        self.primary_key
+    }
+
+    /// Clones the schema without its primary key, e.g. to use it as a template for another
+    /// index. The primary key field itself and its settings are kept.
+    pub fn clone_without_primary_key(&self) -> Schema {
+        Schema { primary_key: None, ..self.clone() }
+    }
+
+    pub fn is_primary_key(&self, id: FieldId) -> bool {
+        self.primary_key == Some(id)
+    }
+
     pub fn set_primary_key(&mut self, name: &str) -> SResult<FieldId> {
         if self.primary_key.is_some() {
             return Err(Error::PrimaryKeyAlreadyPresent)
         }
 
+        self.replace_primary_key(name)
+    }
+
+    /// Returns the primary key, or sets it to the single field named `id` or ending with `_id`,
+    /// ignoring the case, if there is no primary key yet. Returns `None` if there is no such
+    /// field and `AmbiguousPrimaryKey` if there are several.
+    pub fn primary_key_or_guess(&mut self) -> SResult<Option<FieldId>> {
+        if let Some(id) = self.primary_key {
+            return Ok(Some(id));
+        }
+
+        let mut candidates: Vec<_> = self.fields_map
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| {
+                let name = name.to_lowercase();
+                name == "id" || name.ends_with("_id")
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        match candidates.as_slice() {
+            [] => Ok(None),
+            [name] => {
+                let name = name.to_string();
+                self.set_primary_key(&name).map(Some)
+            }
+            _ => {
+                let names = candidates.into_iter().map(ToOwned::to_owned).collect();
+                Err(Error::AmbiguousPrimaryKey(names))
+            }
+        }
+    }
+
+    /// Sets the primary key even if one is already set, e.g. when reconfiguring a schema before
+    /// any document has been indexed. The previous primary key field is kept in the schema.
+    pub fn replace_primary_key(&mut self, name: &str) -> SResult<FieldId> {
         let id = self.insert(name)?;
         self.primary_key = Some(id);
         self.set_indexed(name)?;
@@ -89,6 +555,25 @@ impl Schema {
         Ok(id)
     }
 
+    /// The attribute used to deduplicate the search results.
+    pub fn distinct(&self) -> Option<FieldId> {
+        self.distinct
+    }
+
+    pub fn distinct_name(&self) -> Option<&str> {
+        self.distinct.and_then(|id| self.fields_map.name(id))
+    }
+
+    pub fn set_distinct(&mut self, name: &str) -> SResult<FieldId> {
+        let id = self.insert_field(name)?;
+        self.distinct = Some(id);
+        Ok(id)
+    }
+
+    pub fn clear_distinct(&mut self) {
+        self.distinct = None;
+    }
+
     pub fn id(&self, name: &str) -> Option<FieldId> {
         self.fields_map.id(name)
     }
@@ -101,12 +586,128 @@ impl Schema {
         self.fields_map.iter().map(|(k, _)| k.as_ref())
     }
 
+    /// Iterates over every field of the schema along with the settings it takes part in,
+    /// in no particular order.
+    pub fn iter_fields(&self) -> impl Iterator<Item = FieldInfo<'_>> {
+        self.fields_map.iter().map(move |(name, &id)| FieldInfo { name, metadata: self.field_metadata(id) })
+    }
+
+    fn field_metadata(&self, id: FieldId) -> FieldMetadata {
+        FieldMetadata {
+            id,
+            primary_key: self.primary_key == Some(id),
+            indexed: self.is_field_indexed(id),
+            position: self.indexed_map.get(&id).copied(),
+            displayed: self.is_displayed(id),
+            ranked: self.is_ranked(id),
+            faceted: self.is_faceted(id),
+            distinct: self.distinct == Some(id),
+        }
+    }
+
+    /// Returns the ids of the fields named `prefix` or nested under it using the dot
+    /// notation, e.g. `author` matches `author`, `author.name` and `author.age` but not `authors`.
+    pub fn fields_under_prefix(&self, prefix: &str) -> Vec<FieldId> {
+        let prefix = self.fields_map.key(prefix);
+        let mut ids: Vec<_> = self.fields_map
+            .iter()
+            .filter(|(name, _)| {
+                let name = self.fields_map.key(name);
+                matches!(name.strip_prefix(prefix.as_ref()), Some(rest) if rest.is_empty() || rest.starts_with('.'))
+            })
+            .map(|(_, &id)| id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Returns the sum of the UTF-8 lengths of all the field names, in bytes.
+    pub fn total_field_name_bytes(&self) -> usize {
+        self.names().map(str::len).sum()
+    }
+
+    /// Returns the average UTF-8 length of the field names, in bytes.
+    pub fn average_field_name_len(&self) -> f32 {
+        match self.fields_map.len() {
+            0 => 0.0,
+            len => self.total_field_name_bytes() as f32 / len as f32,
+        }
+    }
+
+    /// The number of fields known by the schema, aliases excluded.
+    pub fn field_count(&self) -> usize {
+        self.fields_map.len()
+    }
+
+    /// Returns an owned snapshot of the fields, keyed by their name as returned by `name`.
+    pub fn as_field_id_map(&self) -> HashMap<String, FieldId> {
+        self.fields_map.iter().map(|(name, &id)| (name.clone(), id)).collect()
+    }
+
     pub fn contains(&self, name: &str) -> bool {
-        self.fields_map.id(name).is_some()
+        self.fields_map.contains(name)
     }
 
     pub fn insert(&mut self, name: &str) -> SResult<FieldId> {
-        self.fields_map.insert(name)
+        self.insert_field(name)
+    }
+
+    /// Inserts every name and returns their ids in the same order, known names are given their
+    /// existing id. The names preceding an invalid one stay inserted.
+    pub fn insert_many<S, I>(&mut self, names: I) -> SResult<Vec<FieldId>>
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = S>,
+    {
+        names.into_iter().map(|name| self.insert_field(name.as_ref())).collect()
+    }
+
+    /// Allocates a field id without binding it to a name, for a two-phase field creation
+    /// completed by `bind_reserved`.
+    pub fn reserve_field_id(&mut self) -> SResult<FieldId> {
+        self.fields_map.reserve()
+    }
+
+    /// Binds a name to an id previously returned by `reserve_field_id`. Fails if the id was
+    /// not reserved, is already bound, or if the name is already used by another field.
+    pub fn bind_reserved(&mut self, id: FieldId, name: &str) -> SResult<()> {
+        if self.locked {
+            return Err(Error::SchemaLocked(name.to_string()));
+        }
+        Schema::validate_field_name_with_max_len(name, self.max_field_name_len())?;
+        self.fields_map.bind_reserved(id, name)
+    }
+
+    /// Makes `alias` resolve to the `target` field, e.g. to keep an old attribute name working
+    /// after a migration. `name` keeps returning the target name and aliases are not part of
+    /// `names`. Fails if `alias` is already a field or if `target` is unknown.
+    pub fn add_alias(&mut self, alias: &str, target: &str) -> SResult<()> {
+        Schema::validate_field_name_with_max_len(alias, self.max_field_name_len())?;
+        self.fields_map.add_alias(alias, target)
+    }
+
+    /// Returns the aliases along with the name of the field they resolve to.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields_map
+            .aliases()
+            .filter_map(move |(alias, id)| self.fields_map.name(id).map(|name| (alias, name)))
+    }
+
+    /// Renames a field, its id is kept so every setting referencing it stays valid.
+    /// Fails if `old` is unknown or if `new` is already used by another field.
+    pub fn rename_field(&mut self, old: &str, new: &str) -> SResult<FieldId> {
+        Schema::validate_field_name_with_max_len(new, self.max_field_name_len())?;
+        self.fields_map.rename(old, new)
+    }
+
+    /// Renames several fields at once, e.g. to swap two names. All the renames are checked
+    /// first: every old name must exist, no new name can be used by a field that is not renamed
+    /// and no two renames can share a new name. On error the schema is left untouched.
+    pub fn rename_many(&mut self, pairs: &[(&str, &str)]) -> SResult<()> {
+        let names: Vec<_> = pairs.iter().map(|(_, new)| *new).collect();
+        self.check_new_names(&names)?;
+        self.fields_map.rename_many(pairs)?;
+        Ok(())
     }
 
     pub fn insert_and_index(&mut self, name: &str) -> SResult<FieldId> {
@@ -121,15 +722,47 @@ impl Schema {
         }
     }
 
-    pub fn ranked(&self) -> &HashSet<FieldId> {
+    /// The ranked attributes, ordered by id.
+    pub fn ranked(&self) -> &BTreeSet<FieldId> {
         &self.ranked
     }
 
+    /// The ranked attributes in the order they were set. Schemas stored before this order was
+    /// kept list their ranked attributes by id.
+    pub fn ranked_ordered(&self) -> Cow<'_, [FieldId]> {
+        if self.ranked_order.len() == self.ranked.len() {
+            Cow::Borrowed(&self.ranked_order)
+        } else {
+            Cow::Owned(self.ranked.iter().copied().collect())
+        }
+    }
+
+    pub fn number_of_ranked(&self) -> usize {
+        self.ranked.len()
+    }
+
     pub fn ranked_name(&self) -> HashSet<&str> {
         self.ranked.iter().filter_map(|a| self.name(*a)).collect()
     }
 
-    pub fn displayed(&self) -> Cow<HashSet<FieldId>> {
+    /// The names of the known fields that are not ranked.
+    pub fn unranked_names(&self) -> HashSet<&str> {
+        self.fields_map
+            .iter()
+            .filter(|(_, id)| !self.ranked.contains(id))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn faceted(&self) -> &HashSet<FieldId> {
+        &self.faceted
+    }
+
+    pub fn faceted_names(&self) -> HashSet<&str> {
+        self.faceted.iter().filter_map(|a| self.name(*a)).collect()
+    }
+
+    pub fn displayed(&self) -> Cow<'_, HashSet<FieldId>> {
         match self.displayed {
             OptionAll::Some(ref v) => Cow::Borrowed(v),
             OptionAll::All => {
@@ -144,6 +777,15 @@ impl Schema {
         }
     }
 
+    /// The number of fields returned by `displayed`, without collecting them.
+    pub fn displayed_len(&self) -> usize {
+        match self.displayed {
+            OptionAll::Some(ref v) => v.len(),
+            OptionAll::All => self.field_count(),
+            OptionAll::None => 0,
+        }
+    }
+
     pub fn is_displayed_all(&self) -> bool {
         self.displayed.is_all()
     }
@@ -156,7 +798,16 @@ impl Schema {
         }
     }
 
-    pub fn indexed(&self) -> Cow<[FieldId]> {
+    /// The names of the known fields that are not displayed, empty when all the fields are.
+    pub fn non_displayed_names(&self) -> HashSet<&str> {
+        self.fields_map
+            .iter()
+            .filter(|(_, &id)| !self.is_displayed(id))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn indexed(&self) -> Cow<'_, [FieldId]> {
         match self.indexed {
             OptionAll::Some(ref v) => Cow::Borrowed(v),
             OptionAll::All => {
@@ -171,18 +822,106 @@ impl Schema {
         }
     }
 
+    /// The number of ids returned by `indexed_ids`, without collecting them. When all the
+    /// fields are indexed only the fields that were given a position are counted.
+    pub fn indexed_len(&self) -> usize {
+        match self.indexed {
+            OptionAll::Some(ref v) => v.len(),
+            OptionAll::All => self.indexed_map.len(),
+            OptionAll::None => 0,
+        }
+    }
+
+    /// Returns the ids of the indexed attributes in position order, when all the fields are
+    /// indexed they are ordered by the position they were given. An empty explicit list of
+    /// indexed attributes gives no id.
+    pub fn indexed_ids(&self) -> Cow<'_, [FieldId]> {
+        match self.indexed {
+            OptionAll::Some(ref v) => Cow::Borrowed(v),
+            OptionAll::All => Cow::Owned(self.positioned_fields()),
+            OptionAll::None => Cow::Owned(Vec::new()),
+        }
+    }
+
+    /// Same as `indexed_ids` without the primary key, whose content is not searched.
+    pub fn indexed_ids_without_primary_key(&self) -> Vec<FieldId> {
+        self.indexed_ids().iter().copied().filter(|id| !self.is_primary_key(*id)).collect()
+    }
+
+    /// Returns the number of attributes that are effectively indexed, to be used as the
+    /// capacity of the structures that are keyed by indexed position.
+    pub fn indexed_attributes_capacity_hint(&self) -> usize {
+        self.indexed_len()
+    }
+
     pub fn indexed_name(&self) -> Vec<&str> {
         self.indexed().iter().filter_map(|a| self.name(*a)).collect()
     }
 
+    /// Returns both `indexed_name` and `displayed_name`, iterating over the fields only once
+    /// when all the fields are indexed and displayed.
+    pub fn effective_indexed_and_displayed(&self) -> (Vec<&str>, HashSet<&str>) {
+        match (&self.indexed, &self.displayed) {
+            (OptionAll::All, OptionAll::All) => {
+                let mut indexed = Vec::with_capacity(self.fields_map.len());
+                let mut displayed = HashSet::with_capacity(self.fields_map.len());
+                for (name, _) in self.fields_map.iter() {
+                    indexed.push(name.as_str());
+                    displayed.insert(name.as_str());
+                }
+                (indexed, displayed)
+            }
+            _ => (self.indexed_name(), self.displayed_name()),
+        }
+    }
+
+    /// Returns `true` if every displayed field is also indexed. This is always the case when all
+    /// the fields are indexed.
+    pub fn indexed_contains_all_displayed(&self) -> bool {
+        self.displayed_not_indexed().is_empty()
+    }
+
+    /// Returns the sorted names of the fields that are displayed but not indexed.
+    pub fn displayed_not_indexed(&self) -> Vec<&str> {
+        if self.is_indexed_all() {
+            return Vec::new();
+        }
+
+        let indexed = self.indexed();
+        let mut names: Vec<_> = self.displayed()
+            .iter()
+            .filter(|id| !indexed.contains(id))
+            .filter_map(|id| self.name(*id))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
     pub fn set_ranked(&mut self, name: &str) -> SResult<FieldId> {
-        let id = self.fields_map.insert(name)?;
-        self.ranked.insert(id);
+        let id = self.insert_field(name)?;
+        if self.ranked.insert(id) {
+            self.ranked_order.push(id);
+        }
+        Ok(id)
+    }
+
+    /// Same as `set_ranked` for a field that must already be known, an unknown field is not
+    /// inserted but reported as not found.
+    pub fn mark_ranked(&mut self, name: &str) -> SResult<FieldId> {
+        if !self.contains(name) {
+            return Err(Error::FieldNameNotFound(name.to_string()));
+        }
+        self.set_ranked(name)
+    }
+
+    pub fn set_faceted(&mut self, name: &str) -> SResult<FieldId> {
+        let id = self.insert_field(name)?;
+        self.faceted.insert(id);
         Ok(id)
     }
 
     pub fn set_displayed(&mut self, name: &str) -> SResult<FieldId> {
-        let id = self.fields_map.insert(name)?;
+        let id = self.insert_field(name)?;
         self.displayed = match self.displayed.take() {
             OptionAll::All => OptionAll::All,
             OptionAll::None => {
@@ -199,27 +938,59 @@ impl Schema {
     }
 
     pub fn set_indexed(&mut self, name: &str) -> SResult<(FieldId, IndexedPos)> {
-        let id = self.fields_map.insert(name)?;
+        let id = self.insert_field(name)?;
 
         if let Some(indexed_pos) = self.indexed_map.get(&id) {
             return Ok((id, *indexed_pos))
         };
-        let pos = self.indexed_map.len() as u16;
-        self.indexed_map.insert(id, pos.into());
+        // the position would wrap around if more than u16::MAX fields were indexed
+        let pos = IndexedPos::try_from(self.indexed_map.len())?;
+        self.indexed_map.insert(id, pos);
         self.indexed = self.indexed.take().map(|mut v| {
             v.push(id);
             v
         });
-        Ok((id, pos.into()))
+        Ok((id, pos))
     }
 
     pub fn clear_ranked(&mut self) {
         self.ranked.clear();
+        self.ranked_order.clear();
+    }
+
+    pub fn clear_faceted(&mut self) {
+        self.faceted.clear();
+    }
+
+    /// Drops the explicit displayed attributes and falls back to displaying every field.
+    /// Note that, unlike `clear_ranked` which leaves no ranked field, clearing the displayed
+    /// attributes makes all of them displayed.
+    pub fn clear_displayed(&mut self) {
+        self.set_all_fields_as_displayed();
+    }
+
+    /// Drops the explicit indexed attributes and falls back to indexing every field.
+    /// Note that, unlike `clear_ranked` which leaves no ranked field, clearing the indexed
+    /// attributes makes all of them indexed.
+    pub fn clear_indexed(&mut self) -> SResult<()> {
+        self.set_all_fields_as_indexed()
     }
 
     pub fn remove_ranked(&mut self, name: &str) {
         if let Some(id) = self.fields_map.id(name) {
-            self.ranked.remove(&id);
+            self.unrank(id);
+        }
+    }
+
+    fn unrank(&mut self, id: FieldId) {
+        if self.ranked.remove(&id) {
+            self.ranked_order.retain(|ranked| *ranked != id);
+        }
+    }
+
+    pub fn remove_faceted(&mut self, name: &str) {
+        if let Some(id) = self.fields_map.id(name) {
+            self.faceted.remove(&id);
         }
     }
 
@@ -236,8 +1007,8 @@ impl Schema {
                 OptionAll::All => {
                     let displayed = self.fields_map
                         .iter()
-                        .filter_map(|(key, &value)| {
-                            if key != name {
+                        .filter_map(|(_, &value)| {
+                            if value != id {
                                 Some(value)
                             } else {
                                 None
@@ -251,26 +1022,481 @@ impl Schema {
         }
     }
 
-    pub fn remove_indexed(&mut self, name: &str) {
-        if let Some(id) = self.fields_map.id(name) {
-            self.indexed_map.remove(&id);
-            self.indexed = match self.indexed.take() {
-                // valid because indexed is All and indexed() return the content of
-                // indexed_map that is already updated
-                OptionAll::All => OptionAll::Some(self.indexed().into_owned()),
-                OptionAll::Some(mut v) => {
-                    v.retain(|x| *x != id);
-                    OptionAll::Some(v)
-                }
-                OptionAll::None => OptionAll::None,
+    /// remove field from indexed attributes, the positions of the following fields are shifted
+    /// so that positions stay contiguous. If indexed attributes is OptionAll::All, it is turned
+    /// into OptionAll::Some(v) where v is all the positioned fields except name.
+    /// Returns the position the field had.
+    pub fn remove_indexed(&mut self, name: &str) -> Option<IndexedPos> {
+        let id = self.fields_map.id(name)?;
+        let removed = self.remove_position(id);
+        self.indexed = match self.indexed.take() {
+            OptionAll::All => OptionAll::Some(self.positioned_fields()),
+            OptionAll::Some(mut v) => {
+                v.retain(|x| *x != id);
+                OptionAll::Some(v)
             }
-        }
-    }
+            OptionAll::None => OptionAll::None,
+        };
+        removed
+    }
+
+    // remove the position of the field and shift the following fields down by one,
+    // returns the position the field had
+    fn remove_position(&mut self, id: FieldId) -> Option<IndexedPos> {
+        let removed = self.indexed_map.remove(&id)?;
+        for pos in self.indexed_map.values_mut() {
+            if *pos > removed {
+                pos.0 -= 1;
+            }
+        }
+        Some(removed)
+    }
+
+    // returns the positioned fields ordered by position
+    fn positioned_fields(&self) -> Vec<FieldId> {
+        let mut positions: Vec<_> = self.indexed_map.iter().map(|(&id, &pos)| (pos, id)).collect();
+        positions.sort_unstable();
+        positions.into_iter().map(|(_, id)| id).collect()
+    }
+
+    // give every field its index in `ordered` as position, other fields lose their position,
+    // the positions are left untouched if there are more fields than positions
+    fn reset_positions(&mut self, ordered: &[FieldId]) -> SResult<()> {
+        self.indexed_map = ordered
+            .iter()
+            .enumerate()
+            .map(|(pos, id)| IndexedPos::try_from(pos).map(|pos| (*id, pos)))
+            .collect::<SResult<_>>()?;
+        Ok(())
+    }
+
+    /// Removes the field from the indexed, displayed and ranked attributes while keeping it
+    /// known by the schema. Returns the settings the field was part of.
+    pub fn clear_field_flags(&mut self, name: &str) -> SResult<FieldMetadata> {
+        let id = self.fields_map.id(name).ok_or_else(|| Error::FieldNameNotFound(name.to_string()))?;
+        let flags = self.field_metadata(id);
+
+        if flags.indexed {
+            self.remove_indexed(name);
+        }
+        if flags.displayed {
+            self.remove_displayed(name);
+        }
+        self.unrank(id);
+        self.faceted.remove(&id);
+        if flags.distinct {
+            self.distinct = None;
+        }
+
+        Ok(flags)
+    }
+
+    /// Repairs a schema where several field ids are bound to the same name, each group of
+    /// duplicates is merged into its lowest id and the settings of the merged fields are united.
+    /// Returns the mapping from every removed id to the id it was merged into.
+    pub fn deduplicate_fields(&mut self) -> SResult<HashMap<FieldId, FieldId>> {
+        let remapping = self.fields_map.deduplicate();
+        self.remap_fields(&remapping)?;
+        Ok(remapping)
+    }
+
+    /// Renumbers the field ids densely from `0`, in the order of the current ids, e.g. after
+    /// many fields were removed. Returns the mapping from every changed id to its new id, so
+    /// that data stored by field id elsewhere can be updated.
+    pub fn compact_ids(&mut self) -> SResult<HashMap<FieldId, FieldId>> {
+        let remapping = self.fields_map.compact();
+        self.remap_fields(&remapping)?;
+        Ok(remapping)
+    }
+
+    // replaces the field ids in every setting, the settings of the fields
+    // remapped to the same id are merged
+    fn remap_fields(&mut self, remapping: &HashMap<FieldId, FieldId>) -> SResult<()> {
+        if remapping.is_empty() {
+            return Ok(());
+        }
+
+        let remap = |id: FieldId| remapping.get(&id).copied().unwrap_or(id);
+
+        self.primary_key = self.primary_key.map(remap);
+        self.distinct = self.distinct.map(remap);
+        self.ranked = self.ranked.iter().copied().map(remap).collect();
+        let mut seen = HashSet::new();
+        self.ranked_order = self.ranked_order
+            .iter()
+            .copied()
+            .map(remap)
+            .filter(|id| seen.insert(*id))
+            .collect();
+        self.faceted = self.faceted.iter().copied().map(remap).collect();
+        if let OptionAll::Some(ref mut v) = self.displayed {
+            *v = v.iter().copied().map(remap).collect();
+        }
+
+        // a merged field keeps the highest priority of its duplicates
+        let mut positioned = self.positioned_fields();
+        for id in &mut positioned {
+            *id = remap(*id);
+        }
+        let mut seen = HashSet::new();
+        positioned.retain(|id| seen.insert(*id));
+        self.reset_positions(&positioned)?;
+
+        if let OptionAll::Some(ref mut v) = self.indexed {
+            let mut seen = HashSet::new();
+            v.retain(|id| seen.insert(remap(*id)));
+            for id in v.iter_mut() {
+                *id = remap(*id);
+            }
+        }
+
+        let frequencies = std::mem::take(&mut self.field_frequencies);
+        for (id, frequency) in frequencies {
+            let entry = self.field_frequencies.entry(remap(id)).or_insert(0);
+            *entry = (*entry).max(frequency);
+        }
+
+        Ok(())
+    }
+
+    /// Moves an indexed field by `delta` positions, a negative delta gives it a higher priority.
+    /// The move is clamped to the first and last positions rather than failing.
+    /// Returns the new position of the field.
+    pub fn move_indexed_relative(&mut self, name: &str, delta: i32) -> SResult<IndexedPos> {
+        let not_found = || Error::FieldNameNotFound(name.to_string());
+        let id = self.fields_map.id(name).ok_or_else(not_found)?;
+        let mut positioned = self.positioned_fields();
+        let from = positioned.iter().position(|x| *x == id).ok_or_else(not_found)?;
+
+        let to = (from as i64 + delta as i64).max(0).min(positioned.len() as i64 - 1) as usize;
+        let id = positioned.remove(from);
+        positioned.insert(to, id);
+        self.set_indexed_order(positioned)?;
+
+        IndexedPos::try_from(to)
+    }
+
+    /// Moves an indexed field to the given position, the fields in between are shifted by one
+    /// position to make room for it.
+    pub fn move_indexed_to_position(&mut self, name: &str, pos: IndexedPos) -> SResult<()> {
+        let not_found = || Error::FieldNameNotFound(name.to_string());
+        let id = self.fields_map.id(name).ok_or_else(not_found)?;
+        let mut positioned = self.positioned_fields();
+        let from = positioned.iter().position(|x| *x == id).ok_or_else(not_found)?;
+
+        let to = usize::from(pos);
+        if to >= positioned.len() {
+            return Err(Error::PositionOutOfRange(pos));
+        }
+        let id = positioned.remove(from);
+        positioned.insert(to, id);
+        self.set_indexed_order(positioned)
+    }
+
+    /// Reverses the order of the indexed attributes, the last one getting the highest priority.
+    pub fn reverse_indexed(&mut self) -> SResult<()> {
+        let mut positioned = self.positioned_fields();
+        positioned.reverse();
+        self.set_indexed_order(positioned)
+    }
+
+    /// Reorders the indexed attributes, `new_order` must be a permutation of the current indexed
+    /// attributes, e.g. the ones returned by `indexed_ids`.
+    pub fn reorder_indexed(&mut self, new_order: &[FieldId]) -> SResult<()> {
+        if let Some(id) = new_order.iter().find(|id| self.fields_map.name(**id).is_none()) {
+            return Err(Error::FieldIdNotFound(*id));
+        }
+
+        let mut current = self.indexed_ids().into_owned();
+        let mut sorted = new_order.to_vec();
+        current.sort_unstable();
+        sorted.sort_unstable();
+        if current != sorted {
+            return Err(Error::IndexedOrderMismatch);
+        }
+
+        self.set_indexed_order(new_order.to_vec())
+    }
+
+    /// Exchanges the fields indexed at positions `a` and `b`, the other positions are untouched.
+    pub fn swap_indexed_positions(&mut self, a: IndexedPos, b: IndexedPos) -> SResult<()> {
+        let field_a = self.field_id_for_position(a)?;
+        let field_b = self.field_id_for_position(b)?;
+
+        self.indexed_map.insert(field_a, b);
+        self.indexed_map.insert(field_b, a);
+        if let OptionAll::Some(ref mut v) = self.indexed {
+            v.swap(a.0 as usize, b.0 as usize);
+        }
+
+        Ok(())
+    }
+
+    // reorder the positioned fields, the explicit indexed list is kept in sync
+    fn set_indexed_order(&mut self, ordered: Vec<FieldId>) -> SResult<()> {
+        self.reset_positions(&ordered)?;
+        if let OptionAll::Some(ref mut v) = self.indexed {
+            *v = ordered;
+        }
+        Ok(())
+    }
+
+    /// Compares the settings of two schemas by field name rather than by field id, two schemas
+    /// built in a different order are equal if they have the same primary key, distinct, ranked,
+    /// displayed, faceted and indexed attributes, the order of the indexed attributes included.
+    pub fn semantically_eq(&self, other: &Schema) -> bool {
+        fn displayed(schema: &Schema) -> Option<HashSet<&str>> {
+            if schema.is_displayed_all() {
+                None
+            } else {
+                Some(schema.displayed_name())
+            }
+        }
+        fn positioned(schema: &Schema) -> Vec<&str> {
+            schema.position_histogram().into_iter().map(|(_, _, name)| name).collect()
+        }
+
+        self.primary_key() == other.primary_key()
+            && self.distinct_name() == other.distinct_name()
+            && self.ranked_name() == other.ranked_name()
+            && self.faceted_names() == other.faceted_names()
+            && displayed(self) == displayed(other)
+            && self.is_indexed_all() == other.is_indexed_all()
+            && positioned(self) == positioned(other)
+    }
+
+    /// Adds the fields and settings of `other` to this schema, fields are matched by name.
+    /// The ranked, faceted and displayed attributes are united and the indexed attributes of
+    /// `other` that are not indexed yet are appended after the current ones, when all the
+    /// fields of `other` are indexed its fields are appended without indexing every field.
+    /// Fails if both schemas have a different primary key, the schema is left untouched then.
+    pub fn merge(&mut self, other: &Schema) -> SResult<()> {
+        let mut schema = self.clone();
+
+        let mut fields: Vec<_> = other.fields_map.iter().map(|(name, &id)| (id, name)).collect();
+        fields.sort_unstable();
+        schema.insert_many(fields.into_iter().map(|(_, name)| name))?;
+
+        if let Some(primary_key) = other.primary_key() {
+            match schema.primary_key() {
+                None => { schema.set_primary_key(primary_key)?; },
+                Some(current) if current != primary_key => return Err(Error::PrimaryKeyAlreadyPresent),
+                Some(_) => (),
+            }
+        }
+        if let (None, Some(distinct)) = (schema.distinct, other.distinct_name()) {
+            schema.set_distinct(distinct)?;
+        }
+
+        for name in other.ranked_ordered().iter().filter_map(|id| other.name(*id)) {
+            schema.set_ranked(name)?;
+        }
+        for name in other.faceted_names() {
+            schema.set_faceted(name)?;
+        }
+
+        if other.is_displayed_all() {
+            schema.set_all_fields_as_displayed();
+        } else {
+            for name in other.displayed_name() {
+                schema.set_displayed(name)?;
+            }
+        }
+
+        for (_, id, name) in other.position_histogram() {
+            if other.is_field_indexed(id) {
+                schema.set_indexed(name)?;
+            }
+        }
+        if other.is_indexed_all() {
+            let mut unpositioned: Vec<_> = other.fields_map
+                .iter()
+                .filter(|(_, id)| !other.indexed_map.contains_key(id))
+                .map(|(name, &id)| (id, name))
+                .collect();
+            unpositioned.sort_unstable();
+            for (_, name) in unpositioned {
+                schema.set_indexed(name)?;
+            }
+        }
+
+        *self = schema;
+        Ok(())
+    }
+
+    /// Replaces the whole state of this schema by a copy of `other`, e.g. to roll back a failed
+    /// settings update. The current state is kept if `other` is inconsistent.
+    pub fn reset_to(&mut self, other: &Schema) -> SResult<()> {
+        other.assert_consistent()?;
+        *self = other.clone();
+        Ok(())
+    }
+
+    /// Checks the invariants of the schema: every field referenced by the settings is known,
+    /// the indexed positions are contiguous and agree with the explicit indexed attributes.
+    /// Under normal operation it never fails, it is meant to be used after a sequence of
+    /// operations in tests or when loading a schema from an untrusted source.
+    pub fn assert_consistent(&self) -> SResult<()> {
+        let inconsistent = |reason: String| Err(Error::InconsistentSchema(reason));
+        let is_known = |id: &FieldId| self.fields_map.name(*id).is_some();
+
+        if let Some(id) = self.primary_key.filter(|id| !is_known(id)) {
+            return inconsistent(format!("unknown primary key id {}", id));
+        }
+        if let Some(id) = self.distinct.filter(|id| !is_known(id)) {
+            return inconsistent(format!("unknown distinct field id {}", id));
+        }
+        if let Some(id) = self.ranked.iter().find(|id| !is_known(id)) {
+            return inconsistent(format!("unknown ranked field id {}", id));
+        }
+        let mut seen = HashSet::new();
+        if let Some(id) = self.ranked_order.iter().find(|id| !self.ranked.contains(id) || !seen.insert(**id)) {
+            return inconsistent(format!("ranked field id {} is not ranked or listed twice", id));
+        }
+        if let Some(id) = self.faceted.iter().find(|id| !is_known(id)) {
+            return inconsistent(format!("unknown faceted field id {}", id));
+        }
+        if let OptionAll::Some(ref v) = self.displayed {
+            if let Some(id) = v.iter().find(|id| !is_known(id)) {
+                return inconsistent(format!("unknown displayed field id {}", id));
+            }
+        }
+        if let Some(id) = self.indexed_map.keys().find(|id| !is_known(id)) {
+            return inconsistent(format!("unknown positioned field id {}", id));
+        }
+
+        if !self.positions_are_dense() {
+            return inconsistent("indexed positions are not contiguous".to_string());
+        }
+
+        if let OptionAll::Some(ref v) = self.indexed {
+            if v.len() != self.indexed_map.len() {
+                return inconsistent("indexed fields and positions differ".to_string());
+            }
+            for (i, id) in v.iter().enumerate() {
+                if self.indexed_map.get(id).map(|pos| usize::from(*pos)) != Some(i) {
+                    let field = match self.name(*id) {
+                        Some(name) => format!("{:?}", name),
+                        None => format!("id {}", id),
+                    };
+                    return inconsistent(format!("indexed field {} is not at position {}", field, i));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Releases the memory left unused after many fields were removed.
+    pub fn shrink_to_fit(&mut self) {
+        self.fields_map.shrink_to_fit();
+        if let OptionAll::Some(ref mut v) = self.displayed {
+            v.shrink_to_fit();
+        }
+        if let OptionAll::Some(ref mut v) = self.indexed {
+            v.shrink_to_fit();
+        }
+        self.indexed_map.shrink_to_fit();
+        self.ranked_order.shrink_to_fit();
+        self.field_frequencies.shrink_to_fit();
+        self.faceted.shrink_to_fit();
+    }
+
+    /// Removes the field from the schema and from every setting, the indexed positions of the
+    /// following fields are shifted so that positions stay contiguous.
+    /// The primary key can't be removed.
+    pub fn remove_field(&mut self, name: &str) -> SResult<()> {
+        let id = self.fields_map.id(name).ok_or_else(|| Error::FieldNameNotFound(name.to_string()))?;
+        if self.primary_key == Some(id) {
+            return Err(Error::PrimaryKeyCannotBeRemoved);
+        }
+        self.purge_fields(&[id])
+    }
+
+    /// Lets new fields reuse the ids of removed fields, the lowest ids being reused first.
+    /// The ids of removed fields are never reused otherwise: the documents may still store
+    /// values under them, call this only once that data was deleted, or a new field would
+    /// inherit it.
+    pub fn recycle_field_ids(&mut self, ids: &[FieldId]) -> SResult<()> {
+        self.fields_map.recycle(ids)
+    }
+
+    /// Keeps only the fields whose name satisfies the `keep` predicate, the primary key is
+    /// always kept. The removed fields are purged from every setting and the remaining indexed
+    /// positions are compacted. Returns the ids of the removed fields.
+    pub fn retain_fields_by<F: Fn(&str) -> bool>(&mut self, keep: F) -> SResult<Vec<FieldId>> {
+        let mut removed: Vec<_> = self.fields_map
+            .iter()
+            .filter(|(name, &id)| Some(id) != self.primary_key && !keep(name))
+            .map(|(_, &id)| id)
+            .collect();
+        removed.sort_unstable();
+
+        self.purge_fields(&removed)?;
+        Ok(removed)
+    }
+
+    /// Keeps only the fields named in `keep`, like `retain_fields_by`, the names are matched
+    /// the way the schema resolves them. The primary key can't be removed: the schema is left
+    /// untouched and the conflicting names are returned in an error if `keep` doesn't name it.
+    pub fn retain_fields(&mut self, keep: &HashSet<&str>) -> SResult<()> {
+        let keep: HashSet<_> = keep.iter().map(|name| self.fields_map.key(name).into_owned()).collect();
+        let kept = |name: &str| keep.contains(self.fields_map.key(name).as_ref());
+
+        if let Some(primary_key) = self.primary_key().filter(|name| !kept(name)) {
+            return Err(Error::FieldsCannotBeRemoved(vec![primary_key.to_string()]));
+        }
+
+        let removed: Vec<_> = self.fields_map
+            .iter()
+            .filter(|(name, _)| !kept(name))
+            .map(|(_, &id)| id)
+            .collect();
+        self.purge_fields(&removed)
+    }
+
+    // remove the fields from the fields map and every setting in a single pass,
+    // the indexed positions of the remaining fields are compacted.
+    fn purge_fields(&mut self, ids: &[FieldId]) -> SResult<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut positioned = self.positioned_fields();
+        positioned.retain(|id| !ids.contains(id));
+        self.reset_positions(&positioned)?;
+
+        for id in ids {
+            if let Some(name) = self.fields_map.name(*id).map(ToOwned::to_owned) {
+                self.fields_map.remove(&name);
+            }
+            self.unrank(*id);
+            self.faceted.remove(id);
+            self.field_frequencies.remove(id);
+        }
+
+        if matches!(self.distinct, Some(id) if ids.contains(&id)) {
+            self.distinct = None;
+        }
+
+        if let OptionAll::Some(ref mut v) = self.displayed {
+            v.retain(|id| !ids.contains(id));
+        }
+
+        if let OptionAll::Some(ref mut v) = self.indexed {
+            v.retain(|id| !ids.contains(id));
+        }
+        Ok(())
+    }
 
     pub fn is_ranked(&self, id: FieldId) -> bool {
         self.ranked.get(&id).is_some()
     }
 
+    pub fn is_faceted(&self, id: FieldId) -> bool {
+        self.faceted.contains(&id)
+    }
+
     pub fn is_displayed(&self, id: FieldId) -> bool {
         match self.displayed {
             OptionAll::Some(ref v) => v.contains(&id),
@@ -283,6 +1509,89 @@ impl Schema {
         self.indexed_map.get(&id)
     }
 
+    /// Returns `true` if the field will be indexed, i.e. it is part of the explicitly indexed
+    /// attributes or all the attributes are indexed and the field has been given a position.
+    pub fn is_field_indexed(&self, id: FieldId) -> bool {
+        match self.indexed {
+            OptionAll::Some(ref v) => v.contains(&id),
+            OptionAll::All => self.indexed_map.contains_key(&id),
+            OptionAll::None => false,
+        }
+    }
+
+    /// Returns the priority of an indexed field for relevancy, `0` being the highest priority.
+    /// Fields that are not indexed have no rank even if they have a position.
+    pub fn indexed_rank(&self, id: FieldId) -> Option<usize> {
+        if !self.is_field_indexed(id) {
+            return None;
+        }
+        self.indexed_map.get(&id).map(|pos| usize::from(*pos))
+    }
+
+    /// Returns a comparator ordering field ids by their indexed position, the fields without a
+    /// position come last, ordered by id.
+    pub fn rank_comparator(&self) -> impl Fn(FieldId, FieldId) -> Ordering + '_ {
+        move |a, b| {
+            let key = |id| match self.indexed_map.get(&id) {
+                Some(pos) => (false, *pos, id),
+                None => (true, IndexedPos(0), id),
+            };
+            key(a).cmp(&key(b))
+        }
+    }
+
+    /// Same as `indexed_rank` for a field name, the index of the field among the indexed
+    /// attributes or `None` if it is not indexed.
+    pub fn indexed_position_of(&self, name: &str) -> Option<usize> {
+        self.id(name).and_then(|id| self.indexed_rank(id))
+    }
+
+    /// Returns the id, name and position of every indexed field, in priority order. When all
+    /// the fields are indexed, the fields that have been given a position are returned.
+    pub fn indexed_attributes_with_ids(&self) -> Vec<(FieldId, &str, IndexedPos)> {
+        self.position_histogram()
+            .into_iter()
+            .filter(|(_, id, _)| self.is_field_indexed(*id))
+            .map(|(pos, id, name)| (id, name, pos))
+            .collect()
+    }
+
+    /// Iterates over every positioned field along with its position, in position order.
+    pub fn indexed_positions(&self) -> impl Iterator<Item = (FieldId, IndexedPos)> {
+        let mut positions: Vec<_> = self.indexed_map.iter().map(|(&id, &pos)| (id, pos)).collect();
+        positions.sort_unstable_by_key(|(_, pos)| *pos);
+        positions.into_iter()
+    }
+
+    /// Returns every occupied indexed position in order along with the field it refers to.
+    pub fn position_histogram(&self) -> Vec<(IndexedPos, FieldId, &str)> {
+        let mut histogram: Vec<_> = self.indexed_map
+            .iter()
+            .filter_map(|(&id, &pos)| self.name(id).map(|name| (pos, id, name)))
+            .collect();
+        histogram.sort_unstable();
+
+        debug_assert!(self.positions_are_dense(), "indexed positions are not contiguous: {:?}", histogram);
+
+        histogram
+    }
+
+    /// Returns every positioned field along with its position, ordered by field id, unlike
+    /// `position_histogram` which is ordered by position.
+    pub fn positions_by_field(&self) -> impl Iterator<Item = (FieldId, IndexedPos)> {
+        let mut positions: Vec<_> = self.indexed_map.iter().map(|(&id, &pos)| (id, pos)).collect();
+        positions.sort_unstable();
+        positions.into_iter()
+    }
+
+    /// Returns `true` if the indexed positions are exactly `0..n`. Under normal operation this
+    /// is always the case, a `false` value denotes a bug or a corrupted schema.
+    pub fn positions_are_dense(&self) -> bool {
+        let mut positions: Vec<_> = self.indexed_map.values().collect();
+        positions.sort_unstable();
+        positions.iter().enumerate().all(|(i, pos)| pos.0 as usize == i)
+    }
+
     pub fn is_indexed_all(&self) -> bool {
         self.indexed.is_all()
     }
@@ -296,15 +1605,43 @@ impl Schema {
             .map(|(&k, _)| k)
     }
 
+    /// Like `indexed_pos_to_field_id` but considers an unoccupied position as an error.
+    pub fn field_id_for_position<I: Into<IndexedPos>>(&self, pos: I) -> SResult<FieldId> {
+        let pos = pos.into();
+        self.indexed_pos_to_field_id(pos).ok_or(Error::PositionOutOfRange(pos))
+    }
+
     pub fn update_ranked<S: AsRef<str>>(&mut self, data: impl IntoIterator<Item = S>) -> SResult<()> {
-        self.ranked.clear();
+        let data: Vec<_> = data.into_iter().collect();
+        self.check_new_names(&data)?;
+
+        self.clear_ranked();
         for name in data {
             self.set_ranked(name.as_ref())?;
         }
         Ok(())
     }
 
+    pub fn update_faceted<S: AsRef<str>>(&mut self, data: impl IntoIterator<Item = S>) -> SResult<()> {
+        let data: Vec<_> = data.into_iter().collect();
+        self.check_new_names(&data)?;
+
+        self.faceted.clear();
+        for name in data {
+            self.set_faceted(name.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the displayed attributes, a lone `"*"` makes all the fields displayed.
     pub fn update_displayed<S: AsRef<str>>(&mut self, data: impl IntoIterator<Item = S>) -> SResult<()> {
+        let data: Vec<_> = data.into_iter().collect();
+        if is_wildcard(&data)? {
+            self.set_all_fields_as_displayed();
+            return Ok(());
+        }
+        self.check_new_names(&data)?;
+
         self.displayed = match self.displayed.take() {
             OptionAll::Some(mut v) => {
                 v.clear();
@@ -318,32 +1655,2379 @@ impl Schema {
         Ok(())
     }
 
+    /// Replaces the indexed attributes, a lone `"*"` makes all the fields indexed.
     pub fn update_indexed<S: AsRef<str>>(&mut self, data: Vec<S>) -> SResult<()> {
-        self.indexed = match self.indexed.take() {
-            OptionAll::Some(mut v) => {
-                v.clear();
-                OptionAll::Some(v)
-            },
-            _ => OptionAll::Some(Vec::new()),
-        };
-        self.indexed_map.clear();
+        if is_wildcard(&data)? {
+            return self.set_all_fields_as_indexed();
+        }
+        self.check_new_names(&data)?;
+
+        let mut seen = HashSet::new();
+        let mut ordered = Vec::with_capacity(data.len());
         for name in data {
-            self.set_indexed(name.as_ref())?;
+            let id = self.insert_field(name.as_ref())?;
+            if seen.insert(id) {
+                ordered.push(id);
+            }
+        }
+
+        // settings are often applied again as is, the positions are kept untouched in that case
+        if matches!(self.indexed, OptionAll::Some(ref v) if *v == ordered) {
+            return Ok(());
         }
+
+        // the fields of the unchanged prefix keep their position so that the data cached by
+        // position stays valid, only the fields that follow are given a new one
+        let kept = self.positioned_fields().iter().zip(&ordered).take_while(|(a, b)| a == b).count();
+        let moved = ordered
+            .iter()
+            .enumerate()
+            .skip(kept)
+            .map(|(pos, id)| IndexedPos::try_from(pos).map(|pos| (*id, pos)))
+            .collect::<SResult<Vec<_>>>()?;
+        self.indexed_map.retain(|_, pos| usize::from(*pos) < kept);
+        self.indexed_map.extend(moved);
+        self.indexed = OptionAll::Some(ordered);
         Ok(())
     }
 
-    pub fn set_all_fields_as_indexed(&mut self) {
-        self.indexed = OptionAll::All;
-        self.indexed_map.clear();
+    /// Returns the names that `update_indexed` would make indexed and the names that would no
+    /// longer be indexed, without modifying the schema. A lone `"*"` stands for every field,
+    /// a `"*"` along with other names is rejected like `update_indexed` does.
+    pub fn difference_indexed(&self, new: &[&str]) -> SResult<(Vec<String>, Vec<String>)> {
+        let new: Vec<&str> = if is_wildcard(new)? {
+            self.fields_map.iter().map(|(name, _)| name.as_str()).collect()
+        } else {
+            new.to_vec()
+        };
+
+        let current = self.indexed_ids();
+        let mut kept = HashSet::new();
+        let mut added = Vec::new();
+        for name in new {
+            match self.id(name) {
+                Some(id) if current.contains(&id) => {
+                    kept.insert(id);
+                }
+                _ if added.iter().any(|a| a == name) => (),
+                _ => added.push(name.to_string()),
+            }
+        }
+
+        let removed = current
+            .iter()
+            .filter(|id| !kept.contains(*id))
+            .filter_map(|id| self.name(*id).map(ToOwned::to_owned))
+            .collect();
+        Ok((added, removed))
+    }
+
+    /// Turns the all indexed mode into an explicit list of the positioned fields, in position
+    /// order. Returns `true` if a conversion happened.
+    pub fn ensure_indexed_explicit(&mut self) -> SResult<bool> {
+        if !self.is_indexed_all() {
+            return Ok(false);
+        }
+
+        let indexed = self.positioned_fields();
+        self.reset_positions(&indexed)?;
+        self.indexed = OptionAll::Some(indexed);
+        Ok(true)
+    }
 
-        for (_name, id) in self.fields_map.iter() {
-            let pos = self.indexed_map.len() as u16;
-            self.indexed_map.insert(*id, pos.into());
+    /// Turns the all displayed mode into an explicit set of all the known fields.
+    /// Returns `true` if a conversion happened.
+    pub fn ensure_displayed_explicit(&mut self) -> bool {
+        if !self.is_displayed_all() {
+            return false;
         }
+
+        self.displayed = OptionAll::Some(self.displayed().into_owned());
+        true
+    }
+
+    /// Reorders the indexed attributes by name. When all the fields are indexed, they are
+    /// turned into an explicit list of all the fields sorted by name.
+    pub fn sort_indexed_alphabetically(&mut self) -> SResult<()> {
+        let mut indexed = self.indexed().into_owned();
+        indexed.sort_unstable_by_key(|id| self.fields_map.name(*id));
+        self.reset_positions(&indexed)?;
+        self.indexed = OptionAll::Some(indexed);
+        Ok(())
+    }
+
+    /// Indexes every field, the fields are given a position in no particular order.
+    /// Fails if there are more fields than positions, the schema is left untouched then.
+    pub fn set_all_fields_as_indexed(&mut self) -> SResult<()> {
+        let fields: Vec<_> = self.fields_map.iter().map(|(_, &id)| id).collect();
+        self.reset_positions(&fields)?;
+        self.indexed = OptionAll::All;
+        Ok(())
     }
 
     pub fn set_all_fields_as_displayed(&mut self) {
         self.displayed = OptionAll::All
     }
+
+    /// Returns the indexed attributes as they are exposed by the settings API: `["*"]` when all
+    /// the fields are indexed, the array of the indexed names otherwise.
+    pub fn indexed_attributes_json(&self) -> Value {
+        if self.is_indexed_all() {
+            json!(["*"])
+        } else {
+            json!(self.indexed_name())
+        }
+    }
+
+    /// Returns the displayed attributes as they are exposed by the settings API: `["*"]` when
+    /// all the fields are displayed, the sorted array of the displayed names otherwise.
+    pub fn displayed_attributes_json(&self) -> Value {
+        if self.is_displayed_all() {
+            json!(["*"])
+        } else {
+            json!(self.displayed_name().into_iter().collect::<BTreeSet<_>>())
+        }
+    }
+
+    /// Returns the ranked attributes as they are exposed by the settings API, in the order they
+    /// were set.
+    pub fn ranked_attributes_json(&self) -> Value {
+        let names: Vec<_> = self.ranked_ordered().iter().filter_map(|id| self.name(*id)).collect();
+        json!(names)
+    }
+
+    /// Computes the changes needed to go from this schema to `other`, comparing fields by name.
+    pub fn diff(&self, other: &Schema) -> SchemaDiff {
+        SchemaDiff::new(self, other)
+    }
+
+    /// Converts this schema to its portable representation using field names.
+    pub fn to_named(&self) -> NamedSchema {
+        let names = |ids: &mut dyn Iterator<Item = &FieldId>| -> BTreeSet<String> {
+            ids.filter_map(|id| self.name(*id)).map(ToOwned::to_owned).collect()
+        };
+        let ordered_names = |ids: &[FieldId]| -> Vec<String> {
+            ids.iter().filter_map(|id| self.name(*id)).map(ToOwned::to_owned).collect()
+        };
+
+        let mut fields: Vec<_> = self.fields_map.iter().map(|(name, &id)| (id, name)).collect();
+        fields.sort_unstable();
+
+        let (indexed, positions) = match self.indexed {
+            OptionAll::Some(ref v) => (Some(ordered_names(v)), Vec::new()),
+            _ => (None, ordered_names(&self.positioned_fields())),
+        };
+
+        NamedSchema {
+            fields: fields.into_iter().map(|(_, name)| name.clone()).collect(),
+            case_insensitive: self.is_case_insensitive(),
+            primary_key: self.primary_key().map(ToOwned::to_owned),
+            distinct: self.distinct_name().map(ToOwned::to_owned),
+            ranked: ordered_names(&self.ranked_ordered()),
+            faceted: names(&mut self.faceted.iter()),
+            displayed: match self.displayed {
+                OptionAll::Some(ref v) => Some(names(&mut v.iter())),
+                _ => None,
+            },
+            indexed,
+            positions,
+        }
+    }
+
+    /// Rebuilds a schema from its portable representation, the fields are given increasing ids
+    /// in the order they are listed.
+    pub fn from_named(named: &NamedSchema) -> SResult<Schema> {
+        let mut schema = if named.case_insensitive { Schema::case_insensitive() } else { Schema::new() };
+        schema.insert_many(&named.fields)?;
+
+        if let Some(ref primary_key) = named.primary_key {
+            schema.primary_key = Some(schema.insert(primary_key)?);
+        }
+        if let Some(ref distinct) = named.distinct {
+            schema.set_distinct(distinct)?;
+        }
+        schema.update_ranked(&named.ranked)?;
+        schema.update_faceted(&named.faceted)?;
+        match named.displayed {
+            Some(ref displayed) => schema.update_displayed(displayed)?,
+            None => schema.set_all_fields_as_displayed(),
+        }
+        match named.indexed {
+            Some(ref indexed) => schema.update_indexed(indexed.iter().collect())?,
+            None => {
+                let mut ordered = schema.insert_many(&named.positions)?;
+                let mut remaining: Vec<_> = schema.fields_map
+                    .iter()
+                    .map(|(_, &id)| id)
+                    .filter(|id| !ordered.contains(id))
+                    .collect();
+                remaining.sort_unstable();
+                ordered.extend(remaining);
+
+                schema.reset_positions(&ordered)?;
+                schema.indexed = OptionAll::All;
+            }
+        }
+
+        schema.assert_consistent()?;
+        Ok(schema)
+    }
+
+    /// Resolves the settings of this schema to field names, the searchable attributes are kept
+    /// in position order and the ranked attributes in the order they were set, while the
+    /// displayed attributes are sorted by name.
+    pub fn to_settings(&self) -> Settings {
+        let wildcard = || vec!["*".to_string()];
+
+        let searchable_attributes = if self.is_indexed_all() {
+            wildcard()
+        } else {
+            self.indexed_name().into_iter().map(ToOwned::to_owned).collect()
+        };
+
+        let displayed_attributes = if self.is_displayed_all() {
+            wildcard()
+        } else {
+            let mut names: Vec<_> = self.displayed_name().into_iter().map(ToOwned::to_owned).collect();
+            names.sort_unstable();
+            names
+        };
+
+        let ranked_attributes = self.ranked_ordered()
+            .iter()
+            .filter_map(|id| self.name(*id))
+            .map(ToOwned::to_owned)
+            .collect();
+
+        Settings {
+            primary_key: self.primary_key().map(ToOwned::to_owned),
+            searchable_attributes: Some(searchable_attributes),
+            displayed_attributes: Some(displayed_attributes),
+            ranked_attributes: Some(ranked_attributes),
+        }
+    }
+
+    /// Applies the given settings, the settings that are `None` are left unchanged while the
+    /// others replace the current ones, e.g. `Some(vec![])` leaves no searchable attribute.
+    /// The schema is left untouched if any of the settings is invalid, all the invalid field
+    /// names are reported at once.
+    pub fn apply_settings(&mut self, settings: &Settings) -> SResult<()> {
+        let names: Vec<&String> = settings.primary_key
+            .iter()
+            .chain(settings.searchable_attributes.iter().flatten().filter(|name| *name != "*"))
+            .chain(settings.displayed_attributes.iter().flatten().filter(|name| *name != "*"))
+            .chain(settings.ranked_attributes.iter().flatten())
+            .collect();
+        self.check_new_names(&names)?;
+
+        let mut schema = self.clone();
+
+        if let Some(ref primary_key) = settings.primary_key {
+            if schema.primary_key() != Some(primary_key.as_str()) {
+                schema.set_primary_key(primary_key)?;
+            }
+        }
+        if let Some(ref searchable) = settings.searchable_attributes {
+            schema.update_indexed(searchable.iter().collect())?;
+        }
+        if let Some(ref displayed) = settings.displayed_attributes {
+            schema.update_displayed(displayed)?;
+        }
+        if let Some(ref ranked) = settings.ranked_attributes {
+            schema.update_ranked(ranked)?;
+        }
+
+        *self = schema;
+        Ok(())
+    }
+
+    /// Describes the settings that differ between this schema and `other`, using the
+    /// client-facing settings format. Each changed setting is given with its `old` and `new`
+    /// value, unchanged settings are omitted.
+    pub fn diff_settings_json(&self, other: &Schema) -> Value {
+        let settings = vec![
+            ("primaryKey", json!(self.primary_key()), json!(other.primary_key())),
+            ("searchableAttributes", self.indexed_attributes_json(), other.indexed_attributes_json()),
+            ("displayedAttributes", self.displayed_attributes_json(), other.displayed_attributes_json()),
+            ("rankedAttributes", self.ranked_attributes_json(), other.ranked_attributes_json()),
+        ];
+
+        let mut diff = Map::new();
+        for (name, old, new) in settings {
+            if old != new {
+                diff.insert(name.to_string(), json!({ "old": old, "new": new }));
+            }
+        }
+
+        Value::Object(diff)
+    }
+
+    /// Describes the primary key field, `None` if there is no primary key.
+    pub fn primary_key_as_field_info(&self) -> Option<FieldInfo<'_>> {
+        let id = self.primary_key?;
+        let name = self.fields_map.name(id)?;
+        Some(FieldInfo { name, metadata: self.field_metadata(id) })
+    }
+
+    pub fn field_usage_report(&self) -> FieldUsageReport {
+        let mut fields: Vec<_> = self.fields_map
+            .iter()
+            .map(|(name, &id)| (name.clone(), self.field_metadata(id)))
+            .collect();
+
+        fields.sort_unstable_by(|(na, a), (nb, b)| {
+            let pa = (a.position.is_none(), a.position);
+            let pb = (b.position.is_none(), b.position);
+            pa.cmp(&pb).then_with(|| na.cmp(nb))
+        });
+
+        FieldUsageReport { fields: fields.into_iter().collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_usage_report() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("zebra").unwrap();
+        schema.insert_and_index("title").unwrap();
+        schema.insert("alpha").unwrap();
+        schema.update_displayed(vec!["id", "title"]).unwrap();
+        schema.update_ranked(vec!["title"]).unwrap();
+        schema.set_faceted("title").unwrap();
+        schema.set_distinct("alpha").unwrap();
+
+        let report = schema.field_usage_report();
+        let names: Vec<_> = report.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["id", "title", "alpha", "zebra"]);
+
+        let id = report.get("id").unwrap();
+        assert!(id.primary_key);
+        assert_eq!(id.position, Some(0.into()));
+        assert!(id.displayed);
+        assert!(!id.ranked);
+
+        let title = report.get("title").unwrap();
+        assert!(!title.primary_key);
+        assert_eq!(title.position, Some(1.into()));
+        assert!(title.displayed);
+        assert!(title.ranked);
+        assert!(title.faceted);
+        assert!(!title.distinct);
+
+        let alpha = report.get("alpha").unwrap();
+        assert_eq!(alpha.position, None);
+        assert!(!alpha.displayed);
+        assert!(!alpha.ranked);
+        assert!(!alpha.faceted);
+        assert!(alpha.distinct);
+    }
+
+    #[test]
+    fn primary_key_as_field_info() {
+        let mut schema = Schema::new();
+        schema.insert_and_index("title").unwrap();
+        assert_eq!(schema.primary_key_as_field_info(), None);
+
+        let id = schema.set_primary_key("uid").unwrap();
+        schema.update_ranked(vec!["uid"]).unwrap();
+        let info = schema.primary_key_as_field_info().unwrap();
+        assert_eq!(info, FieldInfo {
+            name: "uid",
+            metadata: FieldMetadata {
+                id,
+                primary_key: true,
+                indexed: true,
+                position: Some(IndexedPos(1)),
+                displayed: true,
+                ranked: true,
+                faceted: false,
+                distinct: false,
+            },
+        });
+        assert_eq!(serde_json::to_value(info).unwrap(), json!({
+            "name": "uid",
+            "id": id,
+            "primaryKey": true,
+            "indexed": true,
+            "position": 1,
+            "displayed": true,
+            "ranked": true,
+            "faceted": false,
+            "distinct": false,
+        }));
+    }
+
+    #[test]
+    fn diff_settings_json() {
+        let mut old = Schema::with_primary_key("id");
+        old.insert_and_index("title").unwrap();
+        old.insert_and_index("description").unwrap();
+
+        let mut new = old.clone();
+        assert_eq!(old.diff_settings_json(&new), json!({}));
+
+        new.update_indexed(vec!["description", "title"]).unwrap();
+        let expected = json!({
+            "searchableAttributes": {
+                "old": ["*"],
+                "new": ["description", "title"]
+            }
+        });
+        assert_eq!(old.diff_settings_json(&new), expected);
+
+        new.update_displayed(vec!["title", "id"]).unwrap();
+        let diff = old.diff_settings_json(&new);
+        assert_eq!(diff["displayedAttributes"], json!({ "old": ["*"], "new": ["id", "title"] }));
+        assert!(diff["primaryKey"].is_null());
+        assert!(diff["rankedAttributes"].is_null());
+
+        new.update_ranked(vec!["title", "description"]).unwrap();
+        let diff = old.diff_settings_json(&new);
+        assert_eq!(diff["rankedAttributes"], json!({ "old": [], "new": ["title", "description"] }));
+    }
+
+    #[test]
+    fn sort_indexed_alphabetically() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+        schema.insert_and_index("age").unwrap();
+
+        schema.sort_indexed_alphabetically().unwrap();
+        assert!(!schema.is_indexed_all());
+        assert_eq!(schema.indexed_name(), vec!["age", "description", "id", "title"]);
+        for (pos, name) in schema.indexed_name().into_iter().enumerate() {
+            let id = schema.id(name).unwrap();
+            assert_eq!(schema.is_indexed(id), Some(&IndexedPos(pos as u16)));
+        }
+
+        schema.update_indexed(vec!["title", "age"]).unwrap();
+        schema.sort_indexed_alphabetically().unwrap();
+        assert_eq!(schema.indexed_name(), vec!["age", "title"]);
+        assert_eq!(schema.indexed_pos_to_field_id(0), schema.id("age"));
+        assert_eq!(schema.indexed_pos_to_field_id(1), schema.id("title"));
+        assert_eq!(schema.indexed_pos_to_field_id(2), None);
+    }
+
+    #[test]
+    fn field_id_for_position() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        assert_eq!(schema.field_id_for_position(1).unwrap(), title);
+        assert!(matches!(
+            schema.field_id_for_position(2),
+            Err(Error::PositionOutOfRange(IndexedPos(2)))
+        ));
+    }
+
+    #[test]
+    fn retain_fields() {
+        let mut schema = Schema::with_primary_key("_internal_id");
+        let title = schema.insert_and_index("title").unwrap();
+        let internal = schema.insert_and_index("_internal_score").unwrap();
+        let description = schema.insert_and_index("description").unwrap();
+        schema.update_ranked(vec!["_internal_score", "title"]).unwrap();
+        schema.update_displayed(vec!["_internal_score", "description"]).unwrap();
+
+        let removed = schema.retain_fields_by(|name| !name.starts_with("_internal")).unwrap();
+        assert_eq!(removed, vec![internal]);
+        assert_eq!(schema.primary_key(), Some("_internal_id"));
+        assert_eq!(schema.id("_internal_score"), None);
+        assert_eq!(schema.ranked_name(), vec!["title"].into_iter().collect());
+        assert_eq!(schema.displayed_name(), vec!["description"].into_iter().collect());
+        assert_eq!(schema.is_indexed(title), Some(&IndexedPos(1)));
+        assert_eq!(schema.is_indexed(description), Some(&IndexedPos(2)));
+        assert_eq!(schema.indexed_pos_to_field_id(3), None);
+
+        assert_eq!(schema.retain_fields_by(|_| true).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn number_of_ranked() {
+        let mut schema = Schema::with_primary_key("id");
+        assert_eq!(schema.number_of_ranked(), 0);
+        schema.update_ranked(vec!["release_date", "rank"]).unwrap();
+        assert_eq!(schema.number_of_ranked(), 2);
+    }
+
+    #[test]
+    fn ensure_explicit() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+
+        assert!(schema.ensure_indexed_explicit().unwrap());
+        assert!(!schema.is_indexed_all());
+        assert_eq!(schema.indexed_name(), vec!["id", "title", "description"]);
+        assert!(!schema.ensure_indexed_explicit().unwrap());
+
+        assert!(schema.ensure_displayed_explicit());
+        assert!(!schema.is_displayed_all());
+        assert_eq!(schema.displayed_name(), vec!["id", "title", "description"].into_iter().collect());
+        assert!(!schema.ensure_displayed_explicit());
+
+        schema.remove_indexed("title");
+        schema.remove_displayed("title");
+        assert_eq!(schema.indexed_name(), vec!["id", "description"]);
+        assert_eq!(schema.displayed_name(), vec!["id", "description"].into_iter().collect());
+    }
+
+    #[test]
+    fn validate_field_name() {
+        fn reason(name: &str) -> Option<FieldNameError> {
+            match Schema::validate_field_name(name) {
+                Err(Error::InvalidFieldName(field, reason)) => {
+                    assert_eq!(field, name);
+                    Some(reason)
+                }
+                Err(e) => panic!("unexpected error {}", e),
+                Ok(()) => None,
+            }
+        }
+
+        assert_eq!(reason("title"), None);
+        assert_eq!(reason("author.name"), None);
+        assert_eq!(reason(""), Some(FieldNameError::Empty));
+        assert_eq!(reason("  \t"), Some(FieldNameError::Blank));
+        let long = "a".repeat(MAX_FIELD_NAME_LEN + 1);
+        assert_eq!(reason(&long), Some(FieldNameError::TooLong(MAX_FIELD_NAME_LEN)));
+        assert_eq!(reason("ti\ntle"), Some(FieldNameError::ControlCharacter));
+        for name in &[".author", "author.", "author..name"] {
+            assert_eq!(reason(name), Some(FieldNameError::EmptyPathSegment));
+        }
+
+        let mut schema = Schema::with_primary_key("id");
+        assert!(schema.insert("").is_err());
+        assert!(schema.update_indexed(vec!["title", "ti\0tle"]).is_err());
+        assert!(schema.set_displayed("author..name").is_err());
+        assert_eq!(schema.id(""), None);
+        assert_eq!(schema.id("ti\0tle"), None);
+    }
+
+    #[test]
+    fn max_field_name_len() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("description").unwrap();
+        schema.set_max_field_name_len(5);
+        assert_eq!(schema.max_field_name_len(), 5);
+
+        assert!(schema.insert("title").is_ok());
+        assert!(schema.insert("description").is_ok());
+        assert!(matches!(
+            schema.insert("overview"),
+            Err(Error::InvalidFieldName(_, FieldNameError::TooLong(5)))
+        ));
+    }
+
+    #[test]
+    fn position_histogram() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        let description = schema.insert_and_index("description").unwrap();
+        let id = schema.id("id").unwrap();
+        assert_eq!(schema.position_histogram(), vec![
+            (IndexedPos(0), id, "id"),
+            (IndexedPos(1), title, "title"),
+            (IndexedPos(2), description, "description"),
+        ]);
+
+        schema.remove_indexed("title");
+        assert_eq!(schema.indexed_name(), vec!["id", "description"]);
+        assert_eq!(schema.position_histogram(), vec![
+            (IndexedPos(0), id, "id"),
+            (IndexedPos(1), description, "description"),
+        ]);
+    }
+
+    #[test]
+    fn with_defaults_for() {
+        let schema = Schema::with_defaults_for(1000);
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert_eq!(schema.names().count(), 1001);
+        assert_eq!(schema.position_histogram().len(), 1001);
+        let id = schema.id("field_999").unwrap();
+        assert_eq!(schema.is_indexed(id), Some(&IndexedPos(1000)));
+    }
+
+    #[test]
+    fn effective_indexed_and_displayed() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+
+        let (indexed, displayed) = schema.effective_indexed_and_displayed();
+        assert_eq!(indexed, schema.indexed_name());
+        assert_eq!(displayed, schema.displayed_name());
+        assert_eq!(displayed.len(), 3);
+
+        schema.update_indexed(vec!["description", "title"]).unwrap();
+        schema.update_displayed(vec!["title"]).unwrap();
+        let (indexed, displayed) = schema.effective_indexed_and_displayed();
+        assert_eq!(indexed, vec!["description", "title"]);
+        assert_eq!(displayed, vec!["title"].into_iter().collect());
+    }
+
+    #[test]
+    fn clear_field_flags() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        schema.insert("unused").unwrap();
+        schema.update_ranked(vec!["title"]).unwrap();
+        schema.set_faceted("title").unwrap();
+        schema.set_distinct("title").unwrap();
+
+        let flags = schema.clear_field_flags("title").unwrap();
+        assert_eq!(flags, FieldMetadata {
+            id: title,
+            indexed: true,
+            position: Some(IndexedPos(1)),
+            displayed: true,
+            ranked: true,
+            faceted: true,
+            distinct: true,
+            ..FieldMetadata::default()
+        });
+        assert_eq!(schema.id("title"), Some(title));
+        assert!(!schema.is_field_indexed(title));
+        assert!(!schema.is_displayed(title));
+        assert!(!schema.is_ranked(title));
+        assert!(!schema.is_faceted(title));
+        assert_eq!(schema.distinct(), None);
+        assert!(schema.clear_field_flags("title").unwrap().is_empty());
+
+        // every field is displayed by default
+        let flags = schema.clear_field_flags("unused").unwrap();
+        assert_eq!(flags, FieldMetadata { id: flags.id, displayed: true, ..FieldMetadata::default() });
+        assert!(matches!(schema.clear_field_flags("unknown"), Err(Error::FieldNameNotFound(_))));
+    }
+
+    #[test]
+    fn from_multiple_documents() {
+        let documents = vec![
+            vec!["id", "title", "description"],
+            vec!["id", "title"],
+            vec!["id", "title", "rare"],
+        ];
+        let schema = Schema::from_multiple_documents(documents).unwrap();
+        let names: Vec<_> = schema.position_histogram().into_iter().map(|(_, _, name)| name).collect();
+        assert_eq!(names, vec!["id", "title", "description", "rare"]);
+        assert_eq!(schema.field_frequency("id"), Some(3));
+        assert_eq!(schema.field_frequency("title"), Some(3));
+        assert_eq!(schema.field_frequency("description"), Some(1));
+        assert_eq!(schema.field_frequency("rare"), Some(1));
+        assert_eq!(schema.field_frequency("unknown"), None);
+    }
+
+    #[test]
+    fn move_indexed_relative() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["a", "b", "c", "d"]).unwrap();
+
+        assert_eq!(schema.move_indexed_relative("c", -1).unwrap(), IndexedPos(1));
+        assert_eq!(schema.indexed_name(), vec!["a", "c", "b", "d"]);
+        assert_eq!(schema.indexed_pos_to_field_id(1), schema.id("c"));
+
+        assert_eq!(schema.move_indexed_relative("c", 10).unwrap(), IndexedPos(3));
+        assert_eq!(schema.indexed_name(), vec!["a", "b", "d", "c"]);
+
+        assert_eq!(schema.move_indexed_relative("d", -10).unwrap(), IndexedPos(0));
+        assert_eq!(schema.indexed_name(), vec!["d", "a", "b", "c"]);
+        assert_eq!(schema.indexed_pos_to_field_id(0), schema.id("d"));
+
+        assert!(matches!(schema.move_indexed_relative("id", 1), Err(Error::FieldNameNotFound(_))));
+        assert!(matches!(schema.move_indexed_relative("e", 1), Err(Error::FieldNameNotFound(_))));
+    }
+
+    #[test]
+    fn deduplicate_fields() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+        assert!(schema.deduplicate_fields().unwrap().is_empty());
+
+        // simulate a corrupted import where "title" is bound to two ids
+        let duplicate = FieldId(10);
+        schema.fields_map.bind_unchecked(duplicate, "title");
+        schema.update_indexed(vec!["description", "title", "id"]).unwrap();
+        schema.remove_displayed("description");
+        schema.update_ranked(vec!["title"]).unwrap();
+        assert_eq!(schema.id("title"), Some(duplicate));
+
+        let remapping = schema.deduplicate_fields().unwrap();
+        assert_eq!(remapping.len(), 1);
+        assert_eq!(remapping[&duplicate], title);
+        assert_eq!(schema.id("title"), Some(title));
+        assert_eq!(schema.name(duplicate), None);
+        assert!(schema.is_ranked(title));
+        assert!(schema.is_displayed(title));
+        assert_eq!(schema.indexed_name(), vec!["description", "title", "id"]);
+        assert_eq!(schema.is_indexed(title), Some(&IndexedPos(1)));
+        assert_eq!(schema.position_histogram().len(), 3);
+    }
+
+    #[test]
+    fn attributes_json() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+        assert_eq!(schema.indexed_attributes_json(), json!(["*"]));
+        assert_eq!(schema.displayed_attributes_json(), json!(["*"]));
+        assert_eq!(schema.ranked_attributes_json(), json!([]));
+
+        schema.update_indexed(vec!["title", "description"]).unwrap();
+        schema.update_displayed(vec!["title", "id"]).unwrap();
+        assert_eq!(schema.indexed_attributes_json(), json!(["title", "description"]));
+        assert_eq!(schema.displayed_attributes_json(), json!(["id", "title"]));
+
+        schema.update_ranked(vec!["title", "description"]).unwrap();
+        assert_eq!(schema.ranked_attributes_json(), json!(["title", "description"]));
+    }
+
+    #[test]
+    fn primary_key_is_positioned() {
+        let schema = Schema::with_primary_key("id");
+        assert_eq!(schema.is_indexed(schema.id("id").unwrap()), Some(&IndexedPos(0)));
+
+        let mut schema = Schema::new();
+        schema.insert_and_index("title").unwrap();
+        let id = schema.set_primary_key("id").unwrap();
+        assert_eq!(schema.is_indexed(id), Some(&IndexedPos(1)));
+
+        let mut schema = Schema::new();
+        schema.update_indexed(vec!["title"]).unwrap();
+        schema.insert("id").unwrap();
+        let id = schema.set_primary_key("id").unwrap();
+        assert_eq!(schema.is_indexed(id), Some(&IndexedPos(1)));
+        assert_eq!(schema.indexed_name(), vec!["title", "id"]);
+    }
+
+    #[test]
+    fn field_name_bytes() {
+        let mut schema = Schema::new();
+        assert_eq!(schema.total_field_name_bytes(), 0);
+        assert_eq!(schema.average_field_name_len(), 0.0);
+
+        schema.insert("id").unwrap();
+        schema.insert("title").unwrap();
+        schema.insert("café").unwrap();
+        assert_eq!(schema.total_field_name_bytes(), 12);
+        assert_eq!(schema.average_field_name_len(), 4.0);
+    }
+
+    #[test]
+    fn indexed_contains_all_displayed() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+        assert!(schema.indexed_contains_all_displayed());
+
+        schema.update_displayed(vec!["title"]).unwrap();
+        assert!(schema.indexed_contains_all_displayed());
+
+        schema.update_indexed(vec!["title"]).unwrap();
+        assert!(schema.indexed_contains_all_displayed());
+
+        schema.set_all_fields_as_displayed();
+        assert!(!schema.indexed_contains_all_displayed());
+        assert_eq!(schema.displayed_not_indexed(), vec!["description", "id"]);
+
+        schema.update_displayed(vec!["title", "description"]).unwrap();
+        assert_eq!(schema.displayed_not_indexed(), vec!["description"]);
+    }
+
+    #[test]
+    fn reset_to() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.update_ranked(vec!["title"]).unwrap();
+        let snapshot = schema.clone();
+
+        schema.insert_and_index("description").unwrap();
+        schema.update_indexed(vec!["description"]).unwrap();
+        schema.update_displayed(vec!["description"]).unwrap();
+        schema.clear_ranked();
+
+        schema.reset_to(&snapshot).unwrap();
+        assert_eq!(schema.id("description"), None);
+        assert!(schema.is_indexed_all());
+        assert!(schema.is_displayed_all());
+        assert_eq!(schema.ranked_name(), vec!["title"].into_iter().collect());
+        assert_eq!(schema.position_histogram().len(), 2);
+
+        let mut corrupted = snapshot.clone();
+        corrupted.indexed_map.insert(FieldId(42), IndexedPos(2));
+        assert!(matches!(schema.reset_to(&corrupted), Err(Error::InconsistentSchema(_))));
+        assert_eq!(schema.position_histogram().len(), 2);
+    }
+
+    #[test]
+    fn positions_are_dense() {
+        let mut schema = Schema::with_primary_key("id");
+        assert!(schema.positions_are_dense());
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("description").unwrap();
+        schema.insert_and_index("age").unwrap();
+        assert!(schema.positions_are_dense());
+        schema.remove_indexed("title");
+        assert!(schema.positions_are_dense());
+        schema.update_indexed(vec!["age", "id"]).unwrap();
+        assert!(schema.positions_are_dense());
+
+        schema.indexed_map.insert(schema.id("title").unwrap(), IndexedPos(3));
+        assert!(!schema.positions_are_dense());
+    }
+
+    #[test]
+    fn reserve_field_id() {
+        let mut schema = Schema::with_primary_key("id");
+        let reserved = schema.reserve_field_id().unwrap();
+        let title = schema.insert("title").unwrap();
+        assert_ne!(reserved, title);
+        assert_eq!(schema.name(reserved), None);
+
+        assert!(matches!(schema.bind_reserved(reserved, ""), Err(Error::InvalidFieldName(_, FieldNameError::Empty))));
+        assert!(matches!(schema.bind_reserved(title, "date"), Err(Error::FieldIdNotReserved(_))));
+        schema.bind_reserved(reserved, "date").unwrap();
+        assert_eq!(schema.id("date"), Some(reserved));
+        assert!(matches!(schema.bind_reserved(reserved, "date"), Err(Error::FieldIdNotReserved(_))));
+    }
+
+    #[test]
+    fn displayed() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert("title").unwrap();
+        let description = schema.insert("description").unwrap();
+        let id = schema.id("id").unwrap();
+
+        assert!(schema.is_displayed_all());
+        assert_eq!(schema.displayed().into_owned(), vec![id, title, description].into_iter().collect());
+        assert_eq!(schema.displayed_name(), vec!["id", "title", "description"].into_iter().collect());
+
+        schema.update_displayed(vec!["title"]).unwrap();
+        assert!(!schema.is_displayed_all());
+        assert_eq!(schema.displayed().into_owned(), vec![title].into_iter().collect());
+        assert_eq!(schema.displayed_name(), vec!["title"].into_iter().collect());
+    }
+
+    #[test]
+    fn remove_field() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["a", "b", "c", "d"]).unwrap();
+        schema.update_displayed(vec!["b", "c"]).unwrap();
+        schema.update_ranked(vec!["b"]).unwrap();
+        let b = schema.id("b").unwrap();
+
+        schema.remove_field("b").unwrap();
+        assert_eq!(schema.id("b"), None);
+        assert_eq!(schema.name(b), None);
+        assert!(!schema.is_ranked(b));
+        assert!(!schema.is_displayed(b));
+        assert_eq!(schema.is_indexed(b), None);
+        assert_eq!(schema.displayed_name(), vec!["c"].into_iter().collect());
+        assert_eq!(schema.indexed_name(), vec!["a", "c", "d"]);
+        for (pos, name) in ["a", "c", "d"].iter().enumerate() {
+            assert_eq!(schema.indexed_pos_to_field_id(pos as u16), schema.id(name));
+        }
+        assert_eq!(schema.indexed_pos_to_field_id(3), None);
+
+        assert!(matches!(schema.remove_field("b"), Err(Error::FieldNameNotFound(_))));
+        assert!(matches!(schema.remove_field("id"), Err(Error::PrimaryKeyCannotBeRemoved)));
+        assert_eq!(schema.primary_key(), Some("id"));
+    }
+
+    #[test]
+    fn remove_indexed() {
+        let names = |schema: &Schema| -> Vec<_> {
+            schema.position_histogram().into_iter().map(|(pos, _, name)| (pos.0, name.to_string())).collect()
+        };
+
+        let mut schema = Schema::new();
+        schema.update_indexed(vec!["a", "b", "c", "d", "e"]).unwrap();
+
+        assert_eq!(schema.remove_indexed("a"), Some(IndexedPos(0)));
+        assert_eq!(names(&schema), vec![(0, "b".into()), (1, "c".into()), (2, "d".into()), (3, "e".into())]);
+
+        assert_eq!(schema.remove_indexed("c"), Some(IndexedPos(1)));
+        assert_eq!(names(&schema), vec![(0, "b".into()), (1, "d".into()), (2, "e".into())]);
+
+        assert_eq!(schema.remove_indexed("e"), Some(IndexedPos(2)));
+        assert_eq!(names(&schema), vec![(0, "b".into()), (1, "d".into())]);
+        assert_eq!(schema.indexed_name(), vec!["b", "d"]);
+
+        assert_eq!(schema.remove_indexed("e"), None);
+        assert_eq!(schema.remove_indexed("unknown"), None);
+    }
+
+    #[test]
+    fn rename_field() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "description"]).unwrap();
+        schema.update_displayed(vec!["description"]).unwrap();
+        schema.update_ranked(vec!["description"]).unwrap();
+        let description = schema.id("description").unwrap();
+
+        assert_eq!(schema.rename_field("description", "overview").unwrap(), description);
+        assert_eq!(schema.id("description"), None);
+        assert_eq!(schema.id("overview"), Some(description));
+        assert_eq!(schema.name(description), Some("overview"));
+        assert_eq!(schema.is_indexed(description), Some(&IndexedPos(1)));
+        assert_eq!(schema.indexed_name(), vec!["title", "overview"]);
+        assert_eq!(schema.displayed_name(), vec!["overview"].into_iter().collect());
+        assert_eq!(schema.ranked_name(), vec!["overview"].into_iter().collect());
+
+        assert_eq!(schema.rename_field("overview", "overview").unwrap(), description);
+        assert!(matches!(schema.rename_field("overview", "title"), Err(Error::FieldNameAlreadyExists(_))));
+        assert!(matches!(schema.rename_field("unknown", "other"), Err(Error::FieldNameNotFound(_))));
+        assert!(matches!(schema.rename_field("overview", ""), Err(Error::InvalidFieldName(_, FieldNameError::Empty))));
+
+        schema.rename_field("id", "uid").unwrap();
+        assert_eq!(schema.primary_key(), Some("uid"));
+    }
+
+    #[test]
+    fn rename_many() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "description", "poster"]).unwrap();
+        let title = schema.id("title").unwrap();
+        let description = schema.id("description").unwrap();
+
+        schema.rename_many(&[("title", "description"), ("description", "overview"), ("id", "uid")]).unwrap();
+        assert_eq!(schema.id("description"), Some(title));
+        assert_eq!(schema.id("overview"), Some(description));
+        assert_eq!(schema.id("title"), None);
+        assert_eq!(schema.primary_key(), Some("uid"));
+        assert_eq!(schema.indexed_name(), vec!["description", "overview", "poster"]);
+        schema.assert_consistent().unwrap();
+    }
+
+    #[test]
+    fn rename_many_is_atomic() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "description", "poster"]).unwrap();
+        let before = schema.clone();
+
+        let collision = schema.rename_many(&[("title", "name"), ("description", "poster")]);
+        assert!(matches!(collision, Err(Error::FieldNameAlreadyExists(ref name)) if name == "poster"));
+        assert!(schema.semantically_eq(&before));
+        assert_eq!(schema.id("title"), before.id("title"));
+
+        let same_target = schema.rename_many(&[("title", "name"), ("description", "name")]);
+        assert!(matches!(same_target, Err(Error::FieldNameAlreadyExists(_))));
+        let unknown = schema.rename_many(&[("title", "name"), ("unknown", "other")]);
+        assert!(matches!(unknown, Err(Error::FieldNameNotFound(_))));
+        let invalid = schema.rename_many(&[("title", "name"), ("description", "")]);
+        assert!(matches!(invalid, Err(Error::InvalidFields(_))));
+        assert_eq!(schema.indexed_name(), vec!["title", "description", "poster"]);
+    }
+
+    #[test]
+    fn clear_displayed_and_indexed() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title"]).unwrap();
+        schema.update_displayed(vec!["title"]).unwrap();
+        assert!(!schema.is_indexed_all());
+        assert!(!schema.is_displayed_all());
+
+        schema.clear_indexed().unwrap();
+        schema.clear_displayed();
+        assert!(schema.is_indexed_all());
+        assert!(schema.is_displayed_all());
+        assert_eq!(schema.displayed_name(), vec!["id", "title"].into_iter().collect());
+        assert_eq!(schema.position_histogram().len(), 2);
+    }
+
+    #[test]
+    fn swap_indexed_positions() {
+        let mut schema = Schema::new();
+        schema.update_indexed(vec!["a", "b", "c", "d"]).unwrap();
+
+        schema.swap_indexed_positions(IndexedPos(0), IndexedPos(1)).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["b", "a", "c", "d"]);
+        schema.swap_indexed_positions(IndexedPos(3), IndexedPos(1)).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["b", "d", "c", "a"]);
+        for (pos, name) in ["b", "d", "c", "a"].iter().enumerate() {
+            assert_eq!(schema.indexed_pos_to_field_id(pos as u16), schema.id(name));
+        }
+        schema.swap_indexed_positions(IndexedPos(2), IndexedPos(2)).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["b", "d", "c", "a"]);
+
+        assert!(matches!(
+            schema.swap_indexed_positions(IndexedPos(0), IndexedPos(4)),
+            Err(Error::PositionOutOfRange(IndexedPos(4)))
+        ));
+        assert_eq!(schema.indexed_name(), vec!["b", "d", "c", "a"]);
+        assert!(schema.assert_consistent().is_ok());
+    }
+
+    #[test]
+    fn update_indexed_wildcard() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.update_indexed(vec!["title"]).unwrap();
+        assert!(!schema.is_indexed_all());
+
+        schema.update_indexed(vec!["*"]).unwrap();
+        assert!(schema.is_indexed_all());
+        assert_eq!(schema.id("*"), None);
+        assert_eq!(schema.position_histogram().len(), 2);
+
+        assert!(matches!(schema.update_indexed(vec!["title", "*"]), Err(Error::WildcardNotAlone)));
+        assert!(schema.is_indexed_all());
+        assert_eq!(schema.id("*"), None);
+    }
+
+    #[test]
+    fn update_displayed_wildcard() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_displayed(vec!["title"]).unwrap();
+        assert!(!schema.is_displayed_all());
+
+        schema.update_displayed(vec!["*"]).unwrap();
+        assert!(schema.is_displayed_all());
+        assert_eq!(schema.id("*"), None);
+
+        assert!(matches!(schema.update_displayed(vec!["*", "title"]), Err(Error::WildcardNotAlone)));
+        assert!(schema.is_displayed_all());
+        assert_eq!(schema.id("*"), None);
+    }
+
+    #[test]
+    fn fields_under_prefix() {
+        let mut schema = Schema::new();
+        let name = schema.insert("author.name").unwrap();
+        let age = schema.insert("author.age").unwrap();
+        let title = schema.insert("title").unwrap();
+        schema.insert("authors").unwrap();
+
+        assert_eq!(schema.fields_under_prefix("author"), vec![name, age]);
+        assert_eq!(schema.fields_under_prefix("author.name"), vec![name]);
+        assert_eq!(schema.fields_under_prefix("title"), vec![title]);
+        assert!(schema.fields_under_prefix("auth").is_empty());
+        assert!(schema.fields_under_prefix("author.birth").is_empty());
+    }
+
+    #[test]
+    fn fields_under_prefix_case_insensitive() {
+        let mut schema = Schema::case_insensitive();
+        let name = schema.insert("Author.Name").unwrap();
+        schema.insert("title").unwrap();
+
+        assert_eq!(schema.fields_under_prefix("author"), vec![name]);
+        assert_eq!(schema.fields_under_prefix("AUTHOR.name"), vec![name]);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut schema = Schema::case_insensitive();
+        let id = schema.set_primary_key("Id").unwrap();
+        let title = schema.insert("Title").unwrap();
+        assert_eq!(schema.insert("title").unwrap(), title);
+        assert_eq!(schema.id("TITLE"), Some(title));
+        assert_eq!(schema.name(title), Some("Title"));
+        assert_eq!(schema.set_displayed("tItLe").unwrap(), title);
+        assert!(matches!(schema.set_primary_key("id"), Err(Error::PrimaryKeyAlreadyPresent)));
+        assert_eq!(schema.id("id"), Some(id));
+        assert_eq!(schema.primary_key(), Some("Id"));
+
+        schema.remove_displayed("title");
+        assert_eq!(schema.displayed_name(), vec!["Id"].into_iter().collect());
+
+        let mut schema = Schema::new();
+        let title = schema.insert("Title").unwrap();
+        assert_ne!(schema.insert("title").unwrap(), title);
+        assert_eq!(schema.id("TITLE"), None);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut schema = Schema::with_primary_key("id");
+        let name = schema.insert_and_index("name").unwrap();
+        schema.add_alias("title", "name").unwrap();
+
+        assert_eq!(schema.id("title"), Some(name));
+        assert_eq!(schema.name(name), Some("name"));
+        assert_eq!(schema.set_displayed("title").unwrap(), name);
+        assert!(!schema.names().any(|n| n == "title"));
+        assert_eq!(schema.aliases().collect::<Vec<_>>(), vec![("title", "name")]);
+
+        assert!(matches!(schema.add_alias("id", "name"), Err(Error::FieldNameAlreadyExists(_))));
+        assert!(matches!(schema.add_alias("label", "unknown"), Err(Error::FieldNameNotFound(_))));
+        assert!(matches!(schema.add_alias("", "name"), Err(Error::InvalidFieldName(_, FieldNameError::Empty))));
+    }
+
+    #[test]
+    fn recycle_field_ids() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["a", "b", "c"]).unwrap();
+        let b = schema.id("b").unwrap();
+
+        schema.remove_field("b").unwrap();
+        let d = schema.insert_and_index("d").unwrap();
+        assert_ne!(d, b);
+
+        assert!(matches!(schema.recycle_field_ids(&[d]), Err(Error::FieldIdInUse(_))));
+        schema.recycle_field_ids(&[b]).unwrap();
+        let e = schema.insert_and_index("e").unwrap();
+        assert_eq!(e, b);
+        assert_eq!(schema.name(e), Some("e"));
+        assert_eq!(schema.indexed_name(), vec!["a", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn replace_primary_key() {
+        let mut schema = Schema::new();
+        let id = schema.set_primary_key("id").unwrap();
+        assert!(matches!(schema.set_primary_key("uid"), Err(Error::PrimaryKeyAlreadyPresent)));
+
+        let uid = schema.replace_primary_key("uid").unwrap();
+        assert_eq!(schema.primary_key(), Some("uid"));
+        assert_eq!(schema.id("uid"), Some(uid));
+        assert_eq!(schema.id("id"), Some(id));
+        assert!(schema.is_field_indexed(uid));
+
+        assert_eq!(schema.replace_primary_key("id").unwrap(), id);
+        assert_eq!(schema.primary_key(), Some("id"));
+    }
+
+    #[test]
+    fn accept_new_fields() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        assert!(schema.accept_new_fields());
+
+        schema.set_accept_new_fields(false);
+        assert!(!schema.accept_new_fields());
+        assert_eq!(schema.insert("title").unwrap(), title);
+        assert_eq!(schema.insert_and_index("title").unwrap(), title);
+        assert!(matches!(schema.insert("date"), Err(Error::SchemaLocked(_))));
+        assert!(matches!(schema.insert_and_index("date"), Err(Error::SchemaLocked(_))));
+        assert!(matches!(schema.update_displayed(vec!["date"]), Err(Error::SchemaLocked(_))));
+        assert_eq!(schema.id("date"), None);
+
+        schema.set_accept_new_fields(true);
+        assert!(schema.insert("date").is_ok());
+    }
+
+    #[test]
+    fn distinct() {
+        let mut schema = Schema::with_primary_key("id");
+        assert_eq!(schema.distinct_name(), None);
+
+        let id = schema.set_distinct("product_id").unwrap();
+        assert_eq!(schema.id("product_id"), Some(id));
+        assert_eq!(schema.distinct(), Some(id));
+        assert_eq!(schema.distinct_name(), Some("product_id"));
+
+        schema.clear_distinct();
+        assert_eq!(schema.distinct_name(), None);
+        assert!(schema.contains("product_id"));
+
+        schema.set_distinct("product_id").unwrap();
+        schema.remove_field("product_id").unwrap();
+        assert_eq!(schema.distinct(), None);
+    }
+
+    #[test]
+    fn distinct_serde() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.set_distinct("product_id").unwrap();
+
+        let json = serde_json::to_string(&schema).unwrap();
+        let schema: Schema = serde_json::from_str(&json).unwrap();
+        assert_eq!(schema.distinct_name(), Some("product_id"));
+    }
+
+    #[test]
+    fn positions_serde() {
+        let mut schema = Schema::with_primary_key("id");
+        for name in &["title", "overview", "genre"] {
+            schema.insert_and_index(name).unwrap();
+        }
+        schema.swap_indexed_positions(IndexedPos(0), IndexedPos(2)).unwrap();
+        schema.insert("poster").unwrap();
+
+        let json = serde_json::to_value(&schema).unwrap();
+        assert!(json.get("indexed_map").is_none());
+        assert_eq!(json["positions"], json!(schema.positioned_fields()));
+        let rebuilt: Schema = serde_json::from_value(json).unwrap();
+        assert_eq!(rebuilt.indexed_map, schema.indexed_map);
+        assert!(rebuilt.is_indexed_all());
+
+        schema.update_indexed(vec!["genre", "id", "title"]).unwrap();
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["positions"], json!([]));
+        let bytes = bincode::serialize(&schema).unwrap();
+        let rebuilt: Schema = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(rebuilt.indexed_map, schema.indexed_map);
+        assert_eq!(rebuilt.indexed_name(), vec!["genre", "id", "title"]);
+        rebuilt.assert_consistent().unwrap();
+    }
+
+    #[test]
+    fn legacy_positions() {
+        // the stored positions of the legacy layout are ignored in favor of the indexed list
+        let legacy = json!({
+            "fields_map": {
+                "name_map": { "id": 0, "title": 1, "overview": 2 },
+                "id_map": { "0": "id", "1": "title", "2": "overview" },
+                "next_id": 3,
+            },
+            "primary_key": 0,
+            "ranked": [],
+            "displayed": "All",
+            "indexed": { "Some": [2, 1] },
+            "indexed_map": { "1": 4, "2": 0, "0": 1 },
+        });
+        let legacy: LegacySchema = serde_json::from_value(legacy).unwrap();
+        let schema = Schema::try_from(legacy).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["overview", "title"]);
+        assert_eq!(schema.is_indexed(FieldId(1)), Some(&IndexedPos(1)));
+        assert_eq!(schema.is_indexed(FieldId(0)), None);
+        schema.assert_consistent().unwrap();
+    }
+
+    #[test]
+    fn faceted() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_ranked(vec!["release_date"]).unwrap();
+        schema.update_faceted(vec!["genre", "release_date"]).unwrap();
+
+        let genre = schema.id("genre").unwrap();
+        let release_date = schema.id("release_date").unwrap();
+        assert!(schema.is_faceted(genre));
+        assert!(schema.is_faceted(release_date));
+        assert!(!schema.is_ranked(genre));
+        assert_eq!(schema.faceted_names(), vec!["genre", "release_date"].into_iter().collect());
+
+        schema.remove_faceted("release_date");
+        assert!(!schema.is_faceted(release_date));
+        assert!(schema.is_ranked(release_date));
+
+        schema.update_faceted(vec!["author"]).unwrap();
+        assert_eq!(schema.faceted_names(), vec!["author"].into_iter().collect());
+
+        schema.clear_faceted();
+        assert!(schema.faceted().is_empty());
+        assert!(schema.contains("author"));
+    }
+
+    #[test]
+    fn faceted_serde() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_faceted(vec!["genre", "author"]).unwrap();
+
+        let json = serde_json::to_string(&schema).unwrap();
+        let schema: Schema = serde_json::from_str(&json).unwrap();
+        assert_eq!(schema.faceted_names(), vec!["genre", "author"].into_iter().collect());
+    }
+
+    #[test]
+    fn semantically_eq() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("title").unwrap();
+        schema.insert("genre").unwrap();
+        schema.insert("overview").unwrap();
+        schema.update_indexed(vec!["title", "overview"]).unwrap();
+        schema.update_displayed(vec!["title", "genre"]).unwrap();
+        schema.update_ranked(vec!["genre"]).unwrap();
+        schema.update_faceted(vec!["genre"]).unwrap();
+
+        let mut other = Schema::new();
+        other.insert("overview").unwrap();
+        other.insert("genre").unwrap();
+        other.insert("title").unwrap();
+        other.set_primary_key("id").unwrap();
+        other.update_faceted(vec!["genre"]).unwrap();
+        other.update_ranked(vec!["genre"]).unwrap();
+        other.update_displayed(vec!["genre", "title"]).unwrap();
+        other.update_indexed(vec!["title", "overview"]).unwrap();
+
+        assert_ne!(schema.id("title"), other.id("title"));
+        assert!(schema.semantically_eq(&other));
+        assert!(other.semantically_eq(&schema));
+
+        other.update_indexed(vec!["overview", "title"]).unwrap();
+        assert!(!schema.semantically_eq(&other));
+        other.update_indexed(vec!["title", "overview"]).unwrap();
+        assert!(schema.semantically_eq(&other));
+
+        other.set_displayed("overview").unwrap();
+        assert!(!schema.semantically_eq(&other));
+    }
+
+    #[test]
+    fn iter_fields() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("title").unwrap();
+        schema.insert("genre").unwrap();
+        schema.update_indexed(vec!["id", "title"]).unwrap();
+        schema.update_displayed(vec!["title"]).unwrap();
+        schema.update_ranked(vec!["genre"]).unwrap();
+
+        let mut fields: Vec<_> = schema.iter_fields().collect();
+        fields.sort_unstable_by_key(|field| field.metadata.id);
+        assert_eq!(fields, vec![
+            FieldInfo {
+                name: "id",
+                metadata: FieldMetadata {
+                    id: schema.id("id").unwrap(),
+                    primary_key: true,
+                    indexed: true,
+                    position: Some(IndexedPos(0)),
+                    ..FieldMetadata::default()
+                },
+            },
+            FieldInfo {
+                name: "title",
+                metadata: FieldMetadata {
+                    id: schema.id("title").unwrap(),
+                    indexed: true,
+                    position: Some(IndexedPos(1)),
+                    displayed: true,
+                    ..FieldMetadata::default()
+                },
+            },
+            FieldInfo {
+                name: "genre",
+                metadata: FieldMetadata {
+                    id: schema.id("genre").unwrap(),
+                    ranked: true,
+                    ..FieldMetadata::default()
+                },
+            },
+        ]);
+    }
+
+    #[test]
+    fn max_indexed_positions() {
+        let mut schema = Schema::with_primary_key("id");
+        let error = (0..=u16::MAX as usize)
+            .map(|i| schema.insert_and_index(&format!("field_{}", i)))
+            .find_map(Result::err)
+            .unwrap();
+        assert!(matches!(error, Error::MaxFieldsLimitExceeded));
+
+        assert!(schema.positions_are_dense());
+        assert_eq!(schema.indexed_pos_to_field_id(0), schema.id("id"));
+        assert!(schema.set_indexed("overflow").is_err());
+        assert_eq!(schema.indexed_pos_to_field_id(0), schema.id("id"));
+    }
+
+    #[test]
+    fn reset_positions_overflow() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        let before = schema.clone();
+
+        // one more field than there are positions
+        let ordered: Vec<_> = (0..=u16::MAX).map(FieldId).chain(Some(FieldId(0))).collect();
+        assert!(matches!(schema.reset_positions(&ordered), Err(Error::MaxFieldsLimitExceeded)));
+        assert!(matches!(schema.set_indexed_order(ordered), Err(Error::MaxFieldsLimitExceeded)));
+        assert_eq!(schema.position_histogram(), before.position_histogram());
+        assert_eq!(schema.to_named(), before.to_named());
+    }
+
+    #[test]
+    fn to_settings() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("title").unwrap();
+        schema.insert("overview").unwrap();
+        assert_eq!(schema.to_settings(), Settings {
+            primary_key: Some("id".to_string()),
+            searchable_attributes: Some(vec!["*".to_string()]),
+            displayed_attributes: Some(vec!["*".to_string()]),
+            ranked_attributes: Some(Vec::new()),
+        });
+
+        schema.update_indexed(vec!["title", "id", "overview"]).unwrap();
+        schema.update_displayed(vec!["title", "id"]).unwrap();
+        schema.update_ranked(vec!["title"]).unwrap();
+        let settings = schema.to_settings();
+        assert_eq!(settings, Settings {
+            primary_key: Some("id".to_string()),
+            searchable_attributes: Some(vec!["title".to_string(), "id".to_string(), "overview".to_string()]),
+            displayed_attributes: Some(vec!["id".to_string(), "title".to_string()]),
+            ranked_attributes: Some(vec!["title".to_string()]),
+        });
+
+        let mut other = Schema::new();
+        other.apply_settings(&settings).unwrap();
+        assert_eq!(other.to_settings(), settings);
+        assert!(other.semantically_eq(&schema));
+
+        schema.update_ranked(vec!["zeta", "alpha"]).unwrap();
+        let settings = schema.to_settings();
+        assert_eq!(settings.ranked_attributes, Some(vec!["zeta".to_string(), "alpha".to_string()]));
+        let mut other = Schema::new();
+        other.apply_settings(&settings).unwrap();
+        assert_eq!(other.to_settings(), settings);
+    }
+
+    #[test]
+    fn apply_settings_is_atomic() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["id", "title"]).unwrap();
+        let before = schema.to_settings();
+
+        let settings = Settings {
+            searchable_attributes: Some(vec!["overview".to_string()]),
+            displayed_attributes: Some(vec!["*".to_string(), "title".to_string()]),
+            ..Settings::default()
+        };
+        assert!(matches!(schema.apply_settings(&settings), Err(Error::WildcardNotAlone)));
+        assert_eq!(schema.to_settings(), before);
+        assert!(!schema.contains("overview"));
+    }
+
+    #[test]
+    fn apply_partial_settings() {
+        let strings = |names: &[&str]| -> Option<Vec<String>> {
+            Some(names.iter().map(|s| s.to_string()).collect())
+        };
+
+        let mut schema = Schema::new();
+        schema.apply_settings(&Settings::default()).unwrap();
+        assert_eq!(schema.primary_key(), None);
+        assert!(schema.is_indexed_all());
+        assert!(schema.is_displayed_all());
+
+        schema.apply_settings(&Settings { primary_key: Some("id".to_string()), ..Settings::default() }).unwrap();
+        assert_eq!(schema.primary_key(), Some("id"));
+        // setting the same primary key again is a no-op
+        schema.apply_settings(&Settings { primary_key: Some("id".to_string()), ..Settings::default() }).unwrap();
+        let settings = Settings { primary_key: Some("uid".to_string()), ..Settings::default() };
+        assert!(matches!(schema.apply_settings(&settings), Err(Error::PrimaryKeyAlreadyPresent)));
+
+        schema.apply_settings(&Settings { ranked_attributes: strings(&["rank"]), ..Settings::default() }).unwrap();
+        schema.apply_settings(&Settings { searchable_attributes: strings(&["title", "id"]), ..Settings::default() }).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["title", "id"]);
+        assert_eq!(schema.ranked_name(), vec!["rank"].into_iter().collect());
+        assert!(schema.is_displayed_all());
+
+        schema.apply_settings(&Settings { displayed_attributes: strings(&["title"]), ..Settings::default() }).unwrap();
+        assert_eq!(schema.displayed_name(), vec!["title"].into_iter().collect());
+        assert_eq!(schema.indexed_name(), vec!["title", "id"]);
+
+        schema.apply_settings(&Settings { displayed_attributes: strings(&["*"]), ..Settings::default() }).unwrap();
+        assert!(schema.is_displayed_all());
+
+        schema.apply_settings(&Settings { searchable_attributes: strings(&[]), ..Settings::default() }).unwrap();
+        assert!(!schema.is_indexed_all());
+        assert!(schema.indexed_name().is_empty());
+
+        schema.apply_settings(&Settings { ranked_attributes: strings(&[]), ..Settings::default() }).unwrap();
+        assert!(schema.ranked_name().is_empty());
+    }
+
+    #[test]
+    fn contains() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("title").unwrap();
+        schema.add_alias("name", "title").unwrap();
+        assert!(schema.contains("id"));
+        assert!(schema.contains("title"));
+        assert!(schema.contains("name"));
+        assert!(!schema.contains("overview"));
+
+        schema.remove_field("title").unwrap();
+        assert!(!schema.contains("title"));
+        assert!(!schema.contains("name"));
+    }
+
+    #[test]
+    fn insert_many() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert("title").unwrap();
+        let ids = schema.insert_many(vec!["overview", "id", "title", "genre", "overview"]).unwrap();
+
+        let overview = schema.id("overview").unwrap();
+        let genre = schema.id("genre").unwrap();
+        assert_eq!(ids, vec![overview, schema.id("id").unwrap(), title, genre, overview]);
+        assert_eq!(schema.names().count(), 4);
+
+        assert!(schema.insert_many(vec!["author", ""]).is_err());
+        assert!(schema.contains("author"));
+    }
+
+    #[test]
+    fn update_indexed_positions() {
+        let names = vec!["title", "id", "overview", "title", "genre"];
+
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(names.clone()).unwrap();
+
+        let mut naive = Schema::with_primary_key("id");
+        naive.indexed = OptionAll::Some(Vec::new());
+        naive.indexed_map.clear();
+        for name in &names {
+            naive.set_indexed(name).unwrap();
+        }
+
+        assert_eq!(schema.indexed_name(), vec!["title", "id", "overview", "genre"]);
+        assert_eq!(schema.indexed_name(), naive.indexed_name());
+        assert_eq!(schema.indexed_map, naive.indexed_map);
+    }
+
+    #[test]
+    fn update_indexed_many_fields() {
+        let names: Vec<_> = (0..1000).map(|i| format!("field_{}", i)).collect();
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(names.iter().rev().collect()).unwrap();
+
+        assert_eq!(schema.indexed_len(), 1000);
+        assert_eq!(schema.indexed_pos_to_field_id(0), schema.id("field_999"));
+        assert_eq!(schema.indexed_pos_to_field_id(999), schema.id("field_0"));
+        assert!(schema.positions_are_dense());
+    }
+
+    #[test]
+    fn update_indexed_idempotent() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "id", "overview"]).unwrap();
+        let before = format!("{:?}", schema.indexed_map);
+
+        schema.update_indexed(vec!["title", "id", "overview"]).unwrap();
+        assert_eq!(format!("{:?}", schema.indexed_map), before);
+
+        schema.update_indexed(vec!["id", "title", "overview"]).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["id", "title", "overview"]);
+    }
+
+    #[test]
+    fn indexed_ids() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        let overview = schema.insert_and_index("overview").unwrap();
+        let id = schema.id("id").unwrap();
+        assert!(schema.is_indexed_all());
+        assert_eq!(schema.indexed_ids().as_ref(), &[id, title, overview]);
+
+        schema.update_indexed(vec!["overview", "title"]).unwrap();
+        assert_eq!(schema.indexed_ids().as_ref(), &[overview, title]);
+        assert!(matches!(schema.indexed_ids(), Cow::Borrowed(_)));
+
+        schema.update_indexed(Vec::<&str>::new()).unwrap();
+        assert!(schema.indexed_ids().is_empty());
+        schema.indexed = OptionAll::None;
+        assert!(schema.indexed_ids().is_empty());
+
+        schema.update_indexed(vec!["*"]).unwrap();
+        assert_eq!(schema.indexed_ids().len(), schema.indexed_map.len());
+    }
+
+    #[test]
+    fn named_round_trip() {
+        let mut schema = Schema::with_primary_key("id");
+        for name in &["title", "overview", "genre", "release_date"] {
+            schema.insert_and_index(name).unwrap();
+        }
+        schema.remove_field("overview").unwrap();
+        schema.set_distinct("title").unwrap();
+        schema.update_ranked(vec!["release_date"]).unwrap();
+        schema.update_faceted(vec!["genre"]).unwrap();
+        schema.update_displayed(vec!["title", "genre"]).unwrap();
+        schema.swap_indexed_positions(IndexedPos(0), IndexedPos(2)).unwrap();
+
+        let named = schema.to_named();
+        assert!(named.indexed.is_none());
+        let json = serde_json::to_string(&named).unwrap();
+        let named: NamedSchema = serde_json::from_str(&json).unwrap();
+        let rebuilt = Schema::from_named(&named).unwrap();
+        assert!(rebuilt.semantically_eq(&schema));
+        assert_eq!(rebuilt.to_named(), schema.to_named());
+
+        schema.update_indexed(vec!["genre", "title"]).unwrap();
+        let rebuilt = Schema::from_named(&schema.to_named()).unwrap();
+        assert!(rebuilt.semantically_eq(&schema));
+
+        schema.update_ranked(vec!["zeta", "alpha"]).unwrap();
+        let named = schema.to_named();
+        assert_eq!(named.ranked, vec!["zeta", "alpha"]);
+        let rebuilt = Schema::from_named(&named).unwrap();
+        assert_eq!(rebuilt.to_named().ranked, vec!["zeta", "alpha"]);
+    }
+
+    #[test]
+    fn from_named_renumbers_ids() {
+        let named = NamedSchema {
+            fields: vec!["title".to_string(), "id".to_string()],
+            primary_key: Some("id".to_string()),
+            indexed: Some(vec!["title".to_string()]),
+            ..NamedSchema::default()
+        };
+        let schema = Schema::from_named(&named).unwrap();
+        assert_eq!(schema.id("title"), Some(FieldId(0)));
+        assert_eq!(schema.id("id"), Some(FieldId(1)));
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert_eq!(schema.indexed_name(), vec!["title"]);
+        assert!(schema.is_displayed_all());
+
+        let named = NamedSchema { fields: vec!["".to_string()], ..NamedSchema::default() };
+        assert!(Schema::from_named(&named).is_err());
+    }
+
+    #[test]
+    fn with_capacity() {
+        fn build(mut schema: Schema) -> Schema {
+            schema.set_primary_key("id").unwrap();
+            for i in 0..100 {
+                schema.insert_and_index(&format!("field_{}", i)).unwrap();
+            }
+            schema.update_ranked(vec!["field_3"]).unwrap();
+            schema.remove_field("field_10").unwrap();
+            schema
+        }
+
+        let schema = build(Schema::with_capacity(128));
+        let default = build(Schema::default());
+        assert_eq!(schema.to_named(), default.to_named());
+        assert_eq!(schema.position_histogram(), default.position_histogram());
+        assert_eq!(schema.id("field_99"), default.id("field_99"));
+    }
+
+    #[test]
+    fn ranked_order() {
+        let mut schema = Schema::with_primary_key("id");
+        let ids = schema.insert_many(vec!["a", "b", "c", "d", "e"]).unwrap();
+        schema.update_ranked(vec!["e", "b", "d", "a"]).unwrap();
+
+        let expected = vec![ids[0], ids[1], ids[3], ids[4]];
+        for _ in 0..10 {
+            let ranked: Vec<_> = schema.clone().ranked().iter().copied().collect();
+            assert_eq!(ranked, expected);
+        }
+    }
+
+    #[test]
+    fn indexed_rank() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        let overview = schema.insert_and_index("overview").unwrap();
+        let id = schema.id("id").unwrap();
+        assert_eq!(schema.indexed_rank(id), Some(0));
+        assert_eq!(schema.indexed_rank(title), Some(1));
+        assert_eq!(schema.indexed_rank(overview), Some(2));
+
+        schema.update_indexed(vec!["overview", "title"]).unwrap();
+        assert_eq!(schema.indexed_rank(overview), Some(0));
+        assert_eq!(schema.indexed_rank(title), Some(1));
+        assert_eq!(schema.indexed_rank(id), None);
+
+        // a position left behind by a corrupted schema doesn't make a field indexed
+        schema.indexed_map.insert(id, IndexedPos(2));
+        assert_eq!(schema.indexed_rank(id), None);
+    }
+
+    #[test]
+    fn reorder_indexed() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        let overview = schema.insert_and_index("overview").unwrap();
+        let id = schema.id("id").unwrap();
+
+        schema.reorder_indexed(&[overview, id, title]).unwrap();
+        assert!(schema.is_indexed_all());
+        assert_eq!(schema.indexed_ids().as_ref(), &[overview, id, title]);
+
+        schema.update_indexed(vec!["title", "overview"]).unwrap();
+        schema.reorder_indexed(&[overview, title]).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["overview", "title"]);
+        assert_eq!(schema.indexed_rank(title), Some(1));
+
+        assert!(matches!(schema.reorder_indexed(&[title]), Err(Error::IndexedOrderMismatch)));
+        assert!(matches!(schema.reorder_indexed(&[title, id]), Err(Error::IndexedOrderMismatch)));
+        assert!(matches!(
+            schema.reorder_indexed(&[title, title, overview]),
+            Err(Error::IndexedOrderMismatch)
+        ));
+        assert!(matches!(
+            schema.reorder_indexed(&[title, FieldId(42)]),
+            Err(Error::FieldIdNotFound(FieldId(42)))
+        ));
+        assert_eq!(schema.indexed_name(), vec!["overview", "title"]);
+    }
+
+    #[test]
+    fn move_indexed_to_position() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["id", "title", "overview", "genre"]).unwrap();
+
+        schema.move_indexed_to_position("genre", IndexedPos(0)).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["genre", "id", "title", "overview"]);
+        assert_eq!(schema.is_indexed(schema.id("overview").unwrap()), Some(&IndexedPos(3)));
+
+        schema.move_indexed_to_position("id", IndexedPos(3)).unwrap();
+        assert_eq!(schema.indexed_name(), vec!["genre", "title", "overview", "id"]);
+
+        assert!(matches!(
+            schema.move_indexed_to_position("id", IndexedPos(4)),
+            Err(Error::PositionOutOfRange(IndexedPos(4)))
+        ));
+        schema.insert("release_date").unwrap();
+        assert!(matches!(
+            schema.move_indexed_to_position("release_date", IndexedPos(0)),
+            Err(Error::FieldNameNotFound(_))
+        ));
+        assert!(schema.positions_are_dense());
+    }
+
+    #[test]
+    fn field_count() {
+        let mut schema = Schema::new();
+        assert_eq!(schema.field_count(), 0);
+
+        schema.set_primary_key("id").unwrap();
+        schema.insert_many(vec!["title", "overview", "title"]).unwrap();
+        schema.add_alias("name", "title").unwrap();
+        assert_eq!(schema.field_count(), 3);
+
+        schema.remove_field("overview").unwrap();
+        assert_eq!(schema.field_count(), 2);
+    }
+
+    #[test]
+    fn merge() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("title").unwrap();
+        schema.update_indexed(vec!["title", "id"]).unwrap();
+        schema.update_displayed(vec!["title"]).unwrap();
+        schema.update_ranked(vec!["release_date"]).unwrap();
+
+        let mut other = Schema::new();
+        other.insert_many(vec!["genre", "overview", "title"]).unwrap();
+        other.update_indexed(vec!["overview", "title", "genre"]).unwrap();
+        other.update_displayed(vec!["genre"]).unwrap();
+        other.update_ranked(vec!["popularity"]).unwrap();
+        other.update_faceted(vec!["genre"]).unwrap();
+
+        schema.merge(&other).unwrap();
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert_eq!(schema.indexed_name(), vec!["title", "id", "overview", "genre"]);
+        assert_eq!(schema.displayed_name(), vec!["title", "genre"].into_iter().collect());
+        assert_eq!(schema.ranked_name(), vec!["release_date", "popularity"].into_iter().collect());
+        assert_eq!(schema.faceted_names(), vec!["genre"].into_iter().collect());
+        assert!(schema.positions_are_dense());
+
+        let mut all = Schema::with_primary_key("id");
+        all.insert_and_index("author").unwrap();
+        all.insert("editor").unwrap();
+        schema.merge(&all).unwrap();
+        assert!(!schema.is_indexed_all());
+        assert_eq!(schema.indexed_name(), vec!["title", "id", "overview", "genre", "author", "editor"]);
+        assert!(!schema.is_field_indexed(schema.id("popularity").unwrap()));
+        assert!(schema.is_displayed_all());
+        assert_eq!(schema.indexed_pos_to_field_id(4), schema.id("author"));
+        assert!(schema.positions_are_dense());
+
+        let before = schema.to_named();
+        let other = Schema::with_primary_key("uid");
+        assert!(matches!(schema.merge(&other), Err(Error::PrimaryKeyAlreadyPresent)));
+        assert_eq!(schema.to_named(), before);
+    }
+
+    #[test]
+    fn primary_key_id() {
+        let mut schema = Schema::new();
+        assert_eq!(schema.primary_key_id(), None);
+
+        schema.insert("title").unwrap();
+        let id = schema.set_primary_key("id").unwrap();
+        assert_eq!(schema.primary_key_id(), Some(id));
+        assert_eq!(schema.primary_key_id(), schema.id("id"));
+    }
+
+    #[test]
+    fn reverse_indexed() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["id", "title", "overview"]).unwrap();
+
+        schema.reverse_indexed().unwrap();
+        assert_eq!(schema.indexed_name(), vec!["overview", "title", "id"]);
+        assert_eq!(schema.is_indexed(schema.id("overview").unwrap()), Some(&IndexedPos(0)));
+        assert_eq!(schema.is_indexed(schema.id("title").unwrap()), Some(&IndexedPos(1)));
+        assert_eq!(schema.is_indexed(schema.id("id").unwrap()), Some(&IndexedPos(2)));
+
+        schema.set_all_fields_as_indexed().unwrap();
+        let before = schema.indexed_ids().into_owned();
+        schema.reverse_indexed().unwrap();
+        assert!(schema.is_indexed_all());
+        assert_eq!(schema.indexed_ids().iter().rev().copied().collect::<Vec<_>>(), before);
+    }
+
+    #[test]
+    fn unranked_and_non_displayed_names() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_many(vec!["title", "overview", "release_date"]).unwrap();
+        assert!(schema.non_displayed_names().is_empty());
+        assert_eq!(schema.unranked_names(), vec!["id", "title", "overview", "release_date"].into_iter().collect());
+
+        schema.update_ranked(vec!["release_date"]).unwrap();
+        schema.update_displayed(vec!["id", "title"]).unwrap();
+        assert_eq!(schema.unranked_names(), vec!["id", "title", "overview"].into_iter().collect());
+        assert_eq!(schema.non_displayed_names(), vec!["overview", "release_date"].into_iter().collect());
+
+        schema.update_displayed(vec!["*"]).unwrap();
+        assert!(schema.non_displayed_names().is_empty());
+    }
+
+    #[test]
+    fn assert_consistent() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.update_indexed(vec!["id", "title"]).unwrap();
+        schema.update_displayed(vec!["title"]).unwrap();
+        schema.update_ranked(vec!["title"]).unwrap();
+        schema.update_faceted(vec!["title"]).unwrap();
+        schema.set_distinct("title").unwrap();
+        assert!(schema.assert_consistent().is_ok());
+
+        type Corruption = Box<dyn Fn(&mut Schema)>;
+
+        const UNKNOWN: FieldId = FieldId(42);
+        let corruptions: Vec<(&str, Corruption)> = vec![
+            ("primary key", Box::new(|s| s.primary_key = Some(UNKNOWN))),
+            ("distinct", Box::new(|s| s.distinct = Some(UNKNOWN))),
+            ("ranked", Box::new(|s| { s.ranked.insert(UNKNOWN); })),
+            ("faceted", Box::new(|s| { s.faceted.insert(UNKNOWN); })),
+            ("displayed", Box::new(|s| s.displayed = OptionAll::Some(vec![UNKNOWN].into_iter().collect()))),
+            ("positioned", Box::new(|s| { s.indexed_map.insert(UNKNOWN, IndexedPos(2)); })),
+            ("contiguous", Box::new(|s| { s.indexed_map.insert(FieldId(1), IndexedPos(3)); })),
+            ("differ", Box::new(|s| s.indexed = OptionAll::Some(vec![FieldId(0)]))),
+            ("is not at position", Box::new(|s| s.indexed = OptionAll::Some(vec![FieldId(1), FieldId(0)]))),
+        ];
+
+        for (reason, corrupt) in corruptions {
+            let mut corrupted = schema.clone();
+            corrupt(&mut corrupted);
+            match corrupted.assert_consistent() {
+                Err(Error::InconsistentSchema(message)) => assert!(message.contains(reason), "{}", message),
+                other => panic!("{} corruption not detected: {:?}", reason, other),
+            }
+        }
+    }
+
+    #[test]
+    fn positions_by_field() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_many(vec!["title", "overview", "genre"]).unwrap();
+        schema.update_indexed(vec!["genre", "id", "overview"]).unwrap();
+
+        let by_field: Vec<_> = schema.positions_by_field().collect();
+        assert_eq!(by_field, vec![
+            (FieldId(0), IndexedPos(1)),
+            (FieldId(2), IndexedPos(2)),
+            (FieldId(3), IndexedPos(0)),
+        ]);
+
+        let by_position: Vec<_> = schema.position_histogram().into_iter().map(|(pos, id, _)| (id, pos)).collect();
+        assert!(by_position.windows(2).all(|w| w[0].1 < w[1].1));
+        assert!(by_field.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn from_document() {
+        let document = json!({ "id": 1, "overview": "a space opera", "title": "Dune" });
+        let schema = Schema::from_document(document.as_object().unwrap(), Some("id")).unwrap();
+        assert_eq!(schema.primary_key(), Some("id"));
+        let indexed: Vec<_> = schema.indexed_ids().iter().filter_map(|id| schema.name(*id)).collect();
+        assert_eq!(indexed, vec!["id", "overview", "title"]);
+        assert!(schema.is_displayed_all());
+
+        let schema = Schema::from_document(document.as_object().unwrap(), Some("uid")).unwrap();
+        assert_eq!(schema.primary_key(), None);
+        assert_eq!(schema.field_count(), 3);
+    }
+
+    #[test]
+    fn from_nested_document() {
+        let document = json!({
+            "author": { "birth": { "year": 1920 }, "name": "Frank Herbert" },
+            "id": 1,
+            "tags": {},
+        });
+        let schema = Schema::from_document(document.as_object().unwrap(), None).unwrap();
+        assert_eq!(schema.primary_key(), None);
+        let indexed: Vec<_> = schema.indexed_ids().iter().filter_map(|id| schema.name(*id)).collect();
+        assert_eq!(indexed, vec!["author.birth.year", "author.name", "id", "tags"]);
+        assert!(!schema.contains("author"));
+    }
+
+    #[test]
+    fn indexed_ids_without_primary_key() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        let overview = schema.insert_and_index("overview").unwrap();
+        let id = schema.primary_key_id().unwrap();
+        assert!(schema.is_primary_key(id));
+        assert!(!schema.is_primary_key(title));
+        assert_eq!(schema.indexed_ids_without_primary_key(), vec![title, overview]);
+
+        schema.update_indexed(vec!["overview", "title"]).unwrap();
+        assert_eq!(schema.indexed_ids_without_primary_key(), vec![overview, title]);
+
+        let mut schema = Schema::new();
+        let title = schema.insert_and_index("title").unwrap();
+        assert!(!schema.is_primary_key(title));
+        assert_eq!(schema.indexed_ids_without_primary_key(), vec![title]);
+    }
+
+    #[test]
+    fn serialization_is_deterministic() {
+        let names = ["title", "overview", "genre", "release_date", "author", "popularity"];
+
+        let mut schema = Schema::new();
+        for name in &names {
+            schema.insert(name).unwrap();
+        }
+        schema.update_displayed(names.iter().rev()).unwrap();
+        schema.update_faceted(names.iter()).unwrap();
+
+        let mut other = Schema::new();
+        for name in &names {
+            other.insert(name).unwrap();
+        }
+        other.update_displayed(names.iter()).unwrap();
+        other.update_faceted(names.iter().rev()).unwrap();
+
+        let json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(json, serde_json::to_string(&other).unwrap());
+        assert_eq!(json, serde_json::to_string(&schema.clone()).unwrap());
+    }
+
+    #[test]
+    fn indexed_position_of() {
+        let mut schema = Schema::with_primary_key("id");
+        for name in &["title", "overview", "genre", "author"] {
+            schema.insert_and_index(name).unwrap();
+        }
+        assert_eq!(schema.indexed_position_of("genre"), Some(3));
+
+        schema.update_indexed(vec!["author", "title", "genre"]).unwrap();
+        assert_eq!(schema.indexed_position_of("author"), Some(0));
+        assert_eq!(schema.indexed_position_of("title"), Some(1));
+        assert_eq!(schema.indexed_position_of("genre"), Some(2));
+        assert_eq!(schema.indexed_position_of("overview"), None);
+        assert_eq!(schema.indexed_position_of("id"), None);
+        assert_eq!(schema.indexed_position_of("unknown"), None);
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let mut schema = Schema::with_primary_key("id");
+        let result = schema.update_indexed(vec!["ti\ntle", "overview", "", "ti\ntle"]);
+        match result {
+            Err(Error::InvalidFields(names)) => assert_eq!(names, vec!["ti\ntle".to_string(), String::new()]),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(!schema.contains("overview"));
+        assert!(schema.is_indexed_all());
+
+        let settings = Settings {
+            searchable_attributes: Some(vec!["title".to_string(), "a..b".to_string()]),
+            ranked_attributes: Some(vec![" ".to_string()]),
+            ..Settings::default()
+        };
+        match schema.apply_settings(&settings) {
+            Err(Error::InvalidFields(names)) => assert_eq!(names, vec!["a..b".to_string(), " ".to_string()]),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(!schema.contains("title"));
+    }
+
+    #[test]
+    fn clone_without_primary_key() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.update_ranked(vec!["title"]).unwrap();
+        schema.update_faceted(vec!["genre"]).unwrap();
+        schema.update_displayed(vec!["id", "title"]).unwrap();
+
+        let template = schema.clone_without_primary_key();
+        assert_eq!(template.primary_key(), None);
+        assert_eq!(schema.primary_key(), Some("id"));
+        for name in schema.names() {
+            assert_eq!(template.id(name), schema.id(name));
+        }
+        assert_eq!(template.position_histogram(), schema.position_histogram());
+        assert_eq!(template.displayed_name(), schema.displayed_name());
+        assert_eq!(template.ranked_name(), schema.ranked_name());
+        assert_eq!(template.faceted_names(), schema.faceted_names());
+
+        let mut template = template;
+        template.set_primary_key("uid").unwrap();
+    }
+
+    #[test]
+    fn retain_fields_in_set() {
+        let mut schema = Schema::with_primary_key("id");
+        for name in &["title", "overview", "genre"] {
+            schema.insert_and_index(name).unwrap();
+        }
+
+        let keep: HashSet<_> = vec!["title", "genre"].into_iter().collect();
+        let conflict = schema.retain_fields(&keep);
+        assert!(matches!(conflict, Err(Error::FieldsCannotBeRemoved(ref names)) if names == &["id"]));
+        assert_eq!(schema.field_count(), 4);
+
+        let keep: HashSet<_> = vec!["id", "genre"].into_iter().collect();
+        schema.retain_fields(&keep).unwrap();
+        assert!(!schema.contains("title"));
+        assert!(!schema.contains("overview"));
+        assert_eq!(schema.field_count(), 2);
+        assert!(schema.positions_are_dense());
+        assert_eq!(schema.indexed_pos_to_field_id(1), schema.id("genre"));
+    }
+
+    #[test]
+    fn retain_fields_case_insensitive() {
+        let mut schema = Schema::case_insensitive();
+        schema.insert_and_index("Author.Name").unwrap();
+        schema.insert_and_index("title").unwrap();
+
+        let keep: HashSet<_> = vec!["author.name"].into_iter().collect();
+        schema.retain_fields(&keep).unwrap();
+        assert_eq!(schema.field_count(), 1);
+        assert_eq!(schema.name(schema.id("author.name").unwrap()), Some("Author.Name"));
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let names: Vec<_> = (0..1000).map(|i| format!("field_{}", i)).collect();
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(names.iter().collect()).unwrap();
+        schema.update_displayed(names.iter()).unwrap();
+        schema.update_ranked(names.iter()).unwrap();
+
+        schema.retain_fields_by(|name| names[..10].iter().any(|n| n == name)).unwrap();
+        assert_eq!(schema.field_count(), 11);
+
+        schema.shrink_to_fit();
+        assert!(schema.indexed_map.capacity() < 100);
+        assert!(matches!(schema.indexed, OptionAll::Some(ref v) if v.capacity() < 100));
+        assert!(matches!(schema.displayed, OptionAll::Some(ref v) if v.capacity() < 100));
+        assert!(schema.ranked_order.capacity() < 100);
+        assert_eq!(schema.ranked_ordered().len(), 10);
+        assert!(schema.positions_are_dense());
+        assert_eq!(schema.indexed_name().len(), 10);
+    }
+
+    #[test]
+    fn validate_against_document() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert("title").unwrap();
+
+        let validation = schema.validate_against_document(&["title"]).unwrap();
+        assert!(validation.missing_primary_key);
+        assert!(validation.unknown_fields.is_empty());
+        assert!(!validation.is_valid());
+
+        let validation = schema.validate_against_document(&["id", "title", "author"]).unwrap();
+        assert!(!validation.missing_primary_key);
+        assert_eq!(validation.unknown_fields, vec!["author".to_string()]);
+
+        let validation = schema.validate_against_document(&["title", "id"]).unwrap();
+        assert!(validation.is_valid());
+
+        assert!(matches!(Schema::new().validate_against_document(&["id"]), Err(Error::MissingPrimaryKey)));
+    }
+
+    #[test]
+    fn update_indexed_keeps_prefix_positions() {
+        fn position(schema: &Schema, name: &str) -> Option<IndexedPos> {
+            schema.id(name).and_then(|id| schema.is_indexed(id)).copied()
+        }
+
+        let mut schema = Schema::new();
+        schema.update_indexed(vec!["title", "overview", "genre"]).unwrap();
+        let before: Vec<_> = ["title", "overview", "genre"].iter().map(|n| position(&schema, n)).collect();
+
+        schema.update_indexed(vec!["title", "overview", "genre", "release_date"]).unwrap();
+        let after: Vec<_> = ["title", "overview", "genre"].iter().map(|n| position(&schema, n)).collect();
+        assert_eq!(before, after);
+        assert_eq!(position(&schema, "release_date"), Some(IndexedPos(3)));
+
+        schema.update_indexed(vec!["title", "genre", "overview"]).unwrap();
+        assert_eq!(position(&schema, "title"), Some(IndexedPos(0)));
+        assert_eq!(position(&schema, "genre"), Some(IndexedPos(1)));
+        assert_eq!(position(&schema, "overview"), Some(IndexedPos(2)));
+        assert_eq!(position(&schema, "release_date"), None);
+        assert!(schema.positions_are_dense());
+        schema.assert_consistent().unwrap();
+    }
+
+    #[test]
+    fn rank_comparator() {
+        let mut schema = Schema::new();
+        let unindexed = schema.insert("poster").unwrap();
+        schema.update_indexed(vec!["title", "overview", "genre"]).unwrap();
+        let ids: Vec<_> = ["title", "overview", "genre"].iter().map(|n| schema.id(n).unwrap()).collect();
+
+        let compare = schema.rank_comparator();
+        let mut shuffled = vec![ids[2], unindexed, ids[0], ids[1]];
+        shuffled.sort_by(|a, b| compare(*a, *b));
+        assert_eq!(shuffled, vec![ids[0], ids[1], ids[2], unindexed]);
+    }
+
+    #[test]
+    fn indexed_and_displayed_len() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("overview").unwrap();
+        schema.insert("unpositioned").unwrap();
+        assert_eq!(schema.indexed_len(), 3);
+        assert_eq!(schema.displayed_len(), 4);
+        assert_eq!(schema.indexed_len(), schema.indexed_ids().len());
+        assert_eq!(schema.indexed_len(), schema.indexed_attributes_capacity_hint());
+        assert_eq!(schema.displayed_len(), schema.displayed().len());
+
+        schema.update_indexed(vec!["title"]).unwrap();
+        schema.update_displayed(vec!["title", "overview"]).unwrap();
+        assert_eq!(schema.indexed_len(), 1);
+        assert_eq!(schema.displayed_len(), 2);
+        assert_eq!(schema.indexed_len(), schema.indexed_ids().len());
+        assert_eq!(schema.displayed_len(), schema.displayed().len());
+    }
+
+    #[test]
+    fn ranked_ordered() {
+        let mut schema = Schema::new();
+        schema.insert("title").unwrap();
+        schema.insert("rank").unwrap();
+        schema.insert("release_date").unwrap();
+
+        schema.update_ranked(vec!["release_date", "title", "rank"]).unwrap();
+        let names: Vec<_> = schema.ranked_ordered().iter().filter_map(|id| schema.name(*id)).collect();
+        assert_eq!(names, vec!["release_date", "title", "rank"]);
+
+        schema.remove_ranked("title");
+        schema.set_ranked("title").unwrap();
+        let names: Vec<_> = schema.ranked_ordered().iter().filter_map(|id| schema.name(*id)).collect();
+        assert_eq!(names, vec!["release_date", "rank", "title"]);
+        assert!(schema.is_ranked(schema.id("rank").unwrap()));
+        schema.assert_consistent().unwrap();
+
+        schema.clear_ranked();
+        assert!(schema.ranked_ordered().is_empty());
+    }
+
+    #[test]
+    fn insert_known_field_keeps_position() {
+        let mut schema = Schema::with_primary_key("id");
+        let (foo, pos) = schema.set_indexed("foo").unwrap();
+        assert_eq!(schema.set_indexed("foo").unwrap(), (foo, pos));
+        assert_eq!(schema.insert_and_index("foo").unwrap(), foo);
+
+        let occurrences = schema.position_histogram().iter().filter(|(_, _, name)| *name == "foo").count();
+        assert_eq!(occurrences, 1);
+        assert_eq!(schema.is_indexed(foo), Some(&pos));
+        assert_eq!(schema.indexed_attributes_capacity_hint(), 2);
+    }
+
+    #[test]
+    fn indexed_attributes_with_ids() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert_and_index("overview").unwrap();
+        let id = schema.id("id").unwrap();
+        let title = schema.id("title").unwrap();
+        let overview = schema.id("overview").unwrap();
+        assert_eq!(schema.indexed_attributes_with_ids(), vec![
+            (id, "id", IndexedPos(0)),
+            (title, "title", IndexedPos(1)),
+            (overview, "overview", IndexedPos(2)),
+        ]);
+
+        schema.update_indexed(vec!["overview", "title"]).unwrap();
+        assert_eq!(schema.indexed_attributes_with_ids(), vec![
+            (overview, "overview", IndexedPos(0)),
+            (title, "title", IndexedPos(1)),
+        ]);
+    }
+
+    #[test]
+    fn difference_indexed() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title", "overview", "genre"]).unwrap();
+        schema.insert("poster").unwrap();
+
+        let (added, removed) = schema.difference_indexed(&["genre", "poster", "release_date", "title"]).unwrap();
+        assert_eq!(added, vec!["poster", "release_date"]);
+        assert_eq!(removed, vec!["overview"]);
+        assert_eq!(schema.indexed_name(), vec!["title", "overview", "genre"]);
+
+        let (mut added, removed) = schema.difference_indexed(&["*"]).unwrap();
+        added.sort_unstable();
+        assert_eq!(added, vec!["id", "poster"]);
+        assert!(removed.is_empty());
+
+        assert!(matches!(schema.difference_indexed(&["*", "title"]), Err(Error::WildcardNotAlone)));
+        assert!(matches!(schema.update_indexed(vec!["*", "title"]), Err(Error::WildcardNotAlone)));
+    }
+
+    #[test]
+    fn compact_ids() {
+        let mut schema = Schema::with_primary_key("id");
+        let names: Vec<_> = (0..10).map(|i| format!("field_{}", i)).collect();
+        for name in &names {
+            schema.insert_and_index(name).unwrap();
+        }
+        for name in &names[..6] {
+            schema.remove_field(name).unwrap();
+        }
+        schema.update_indexed(vec!["field_9", "field_7", "id"]).unwrap();
+        schema.update_displayed(vec!["field_8", "field_9"]).unwrap();
+        schema.update_ranked(vec!["field_9", "field_6"]).unwrap();
+        schema.set_faceted("field_7").unwrap();
+        schema.set_distinct("field_8").unwrap();
+        let before = schema.clone();
+
+        let remapping = schema.compact_ids().unwrap();
+        assert_eq!(remapping.len(), 4);
+        let mut ids: Vec<_> = schema.iter_fields().map(|field| field.metadata.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..5).map(FieldId).collect::<Vec<_>>());
+        assert_eq!(remapping[&before.id("field_9").unwrap()], schema.id("field_9").unwrap());
+
+        assert!(schema.semantically_eq(&before));
+        assert_eq!(schema.indexed_name(), vec!["field_9", "field_7", "id"]);
+        let ranked: Vec<_> = schema.ranked_ordered().iter().filter_map(|id| schema.name(*id)).collect();
+        assert_eq!(ranked, vec!["field_9", "field_6"]);
+        assert_eq!(schema.primary_key(), Some("id"));
+        schema.assert_consistent().unwrap();
+
+        assert!(schema.compact_ids().unwrap().is_empty());
+        assert_eq!(schema.insert("field_10").unwrap(), FieldId(5));
+    }
+
+    #[test]
+    fn indexed_positions() {
+        let mut schema = Schema::new();
+        schema.update_indexed(vec!["title", "overview", "genre"]).unwrap();
+
+        let expected: Vec<_> = (0..3)
+            .map(IndexedPos)
+            .map(|pos| (schema.indexed_pos_to_field_id(pos).unwrap(), pos))
+            .collect();
+        assert_eq!(schema.indexed_positions().collect::<Vec<_>>(), expected);
+        assert_eq!(schema.indexed_positions().next(), Some((schema.id("title").unwrap(), IndexedPos(0))));
+    }
+
+    #[test]
+    fn mark_ranked() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert("title").unwrap();
+
+        assert_eq!(schema.mark_ranked("title").unwrap(), title);
+        assert!(schema.is_ranked(title));
+
+        let unknown = schema.mark_ranked("release_date");
+        assert!(matches!(unknown, Err(Error::FieldNameNotFound(ref name)) if name == "release_date"));
+        assert!(!schema.contains("release_date"));
+        assert_eq!(schema.number_of_ranked(), 1);
+    }
+
+    #[test]
+    fn missing_field_is_reported_by_name() {
+        fn not_found<T>(result: SResult<T>) -> Option<String> {
+            match result {
+                Err(Error::FieldNameNotFound(name)) => Some(name),
+                _ => None,
+            }
+        }
+
+        let mut schema = Schema::with_primary_key("id");
+        schema.update_indexed(vec!["title"]).unwrap();
+        let before = schema.clone();
+
+        let missing = Some("missing".to_string());
+        assert_eq!(not_found(schema.remove_field("missing")), missing);
+        assert_eq!(not_found(schema.rename_field("missing", "other")), missing);
+        assert_eq!(not_found(schema.mark_ranked("missing")), missing);
+        assert_eq!(not_found(schema.clear_field_flags("missing")), missing);
+        assert_eq!(not_found(schema.move_indexed_relative("missing", 1)), missing);
+        assert_eq!(not_found(schema.move_indexed_to_position("missing", IndexedPos(0))), missing);
+        assert!(schema.semantically_eq(&before));
+    }
+
+    #[test]
+    fn primary_key_or_guess() {
+        let mut schema = Schema::new();
+        schema.insert("title").unwrap();
+        schema.insert("valid").unwrap();
+        assert_eq!(schema.primary_key_or_guess().unwrap(), None);
+        assert_eq!(schema.primary_key(), None);
+
+        let movie_id = schema.insert("Movie_ID").unwrap();
+        assert_eq!(schema.primary_key_or_guess().unwrap(), Some(movie_id));
+        assert_eq!(schema.primary_key(), Some("Movie_ID"));
+
+        schema.insert("id").unwrap();
+        assert_eq!(schema.primary_key_or_guess().unwrap(), Some(movie_id));
+
+        let mut schema = Schema::new();
+        schema.insert("user_id").unwrap();
+        schema.insert("id").unwrap();
+        let ambiguous = schema.primary_key_or_guess();
+        assert!(matches!(ambiguous, Err(Error::AmbiguousPrimaryKey(ref names)) if names == &["id", "user_id"]));
+        assert_eq!(schema.primary_key(), None);
+    }
+
+    #[test]
+    fn as_field_id_map() {
+        let mut schema = Schema::case_insensitive();
+        let mut expected = HashMap::new();
+        for name in &["id", "Title", "overview"] {
+            expected.insert(name.to_string(), schema.insert(name).unwrap());
+        }
+
+        let snapshot = schema.as_field_id_map();
+        schema.insert("genre").unwrap();
+        assert_eq!(snapshot, expected);
+        assert_eq!(snapshot.len(), 3);
+    }
+
+    #[test]
+    fn try_with_primary_key() {
+        let schema = Schema::try_with_primary_key("id").unwrap();
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert!(schema.semantically_eq(&Schema::with_primary_key("id")));
+
+        let empty = Schema::try_with_primary_key("");
+        assert!(matches!(empty, Err(Error::InvalidFieldName(ref name, FieldNameError::Empty)) if name.is_empty()));
+        let blank = Schema::try_with_primary_key("  ");
+        assert!(matches!(blank, Err(Error::InvalidFieldName(_, FieldNameError::Blank))));
+
+        // the infallible constructor doesn't validate the name
+        assert_eq!(Schema::with_primary_key("a..b").primary_key(), Some("a..b"));
+    }
+
+    #[test]
+    fn is_field_indexed() {
+        let mut schema = Schema::with_primary_key("id");
+        let title = schema.insert_and_index("title").unwrap();
+        let unpositioned = schema.insert("unpositioned").unwrap();
+        assert!(schema.is_field_indexed(schema.id("id").unwrap()));
+        assert!(schema.is_field_indexed(title));
+        assert!(!schema.is_field_indexed(unpositioned));
+
+        schema.update_indexed(vec!["title"]).unwrap();
+        assert!(!schema.is_field_indexed(schema.id("id").unwrap()));
+        assert!(schema.is_field_indexed(title));
+        assert!(!schema.is_field_indexed(unpositioned));
+    }
+
+    #[test]
+    fn indexed_attributes_capacity_hint() {
+        let mut schema = Schema::with_primary_key("id");
+        schema.insert_and_index("title").unwrap();
+        schema.insert("unpositioned").unwrap();
+        assert_eq!(schema.indexed_attributes_capacity_hint(), 2);
+
+        schema.update_indexed(vec!["title"]).unwrap();
+        assert_eq!(schema.indexed_attributes_capacity_hint(), 1);
+    }
 }
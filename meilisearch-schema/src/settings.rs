@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// The settings of a schema expressed with field names, as they are exposed to the users.
+///
+/// The searchable and displayed attributes are `["*"]` when all the fields are searchable or
+/// displayed. When applied to a schema, a `None` value leaves the setting unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub primary_key: Option<String>,
+    pub searchable_attributes: Option<Vec<String>>,
+    pub displayed_attributes: Option<Vec<String>>,
+    pub ranked_attributes: Option<Vec<String>>,
+}